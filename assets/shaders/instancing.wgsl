@@ -1,4 +1,4 @@
-#import bevy_pbr::mesh_functions::{get_world_from_local, mesh_position_local_to_clip}
+#import bevy_pbr::mesh_functions::{get_world_from_local, mesh_normal_local_to_world, mesh_position_local_to_clip}
 
 struct Vertex {
     @location(0) position: vec3<f32>,
@@ -7,13 +7,31 @@ struct Vertex {
 
     @location(3) i_pos_scale: vec4<f32>,
     @location(4) i_color: vec4<f32>,
+    @location(5) i_ao: vec2<u32>,
 };
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) color: vec4<f32>,
+    @location(1) world_normal: vec3<f32>,
+    @location(2) local_normal: vec3<f32>,
+    @location(3) uv: vec2<f32>,
+    // 0 for a corner hidden by its neighbours, 1 for an open one
+    @location(4) ao: f32,
 };
 
+// same layout as `LightUniform` in lighting.rs
+struct Light {
+    // world space direction towards the light
+    direction: vec3<f32>,
+    intensity: f32,
+    ambient: f32,
+    face_shading: f32,
+    ao_strength: f32,
+};
+
+@group(2) @binding(0) var<uniform> light: Light;
+
 @vertex
 fn vertex(vertex: Vertex) -> VertexOutput {
     let position = vertex.position * vertex.i_pos_scale.w + vertex.i_pos_scale.xyz;
@@ -27,11 +45,67 @@ fn vertex(vertex: Vertex) -> VertexOutput {
         vec4<f32>(position, 1.0)
     );
     out.color = vertex.i_color;
+    out.world_normal = mesh_normal_local_to_world(vertex.normal, 0u);
+    out.local_normal = vertex.normal;
+    out.uv = vertex.uv;
+    out.ao = corner_ao(vertex.normal, vertex.position, vertex.i_ao);
     return out;
 }
 
+// Reads the occlusion of this vertex from the instance, packed two bits per
+// corner in the face and corner order of `vertex_ao` in mesh.rs.
+fn corner_ao(normal: vec3<f32>, position: vec3<f32>, ao: vec2<u32>) -> f32 {
+    let x = vec3<f32>(1.0, 0.0, 0.0);
+    let y = vec3<f32>(0.0, 1.0, 0.0);
+    let z = vec3<f32>(0.0, 0.0, 1.0);
+    var face = 0u;
+    var u = y;
+    var v = z;
+    if normal.x < -0.5 {
+        face = 1u;
+        u = z;
+        v = y;
+    } else if normal.y > 0.5 {
+        face = 2u;
+        u = z;
+        v = x;
+    } else if normal.y < -0.5 {
+        face = 3u;
+        u = x;
+        v = z;
+    } else if normal.z > 0.5 {
+        face = 4u;
+        u = x;
+        v = y;
+    } else if normal.z < -0.5 {
+        face = 5u;
+        u = y;
+        v = x;
+    }
+    let corner = select(0u, 1u, dot(position, u) > 0.0) + select(0u, 2u, dot(position, v) > 0.0);
+    let bit = (face * 4u + corner) * 2u;
+    let word = select(ao.x, ao.y, bit >= 32u);
+    return f32((word >> (bit % 32u)) & 3u) / 3.0;
+}
+
+// Brightness of a face from its orientation in the grid, with a darker border
+// so the faces of neighbouring cubes do not blend into one.
+fn face_shade(normal: vec3<f32>, uv: vec2<f32>) -> f32 {
+    var shade = dot(abs(normal), vec3<f32>(0.85, 1.0, 0.7));
+    if normal.y < -0.5 {
+        shade = 0.55;
+    }
+    let edge = min(min(uv.x, 1.0 - uv.x), min(uv.y, 1.0 - uv.y));
+    return shade * mix(0.6, 1.0, smoothstep(0.0, 0.08, edge));
+}
+
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
-    return in.color;
+    let diffuse = max(dot(normalize(in.world_normal), light.direction), 0.0);
+    var shade = light.ambient + light.intensity * diffuse;
+    shade *= 1.0 - light.ao_strength * (1.0 - in.ao);
+    if light.face_shading > 0.5 {
+        shade *= face_shade(in.local_normal, in.uv);
+    }
+    return vec4<f32>(in.color.rgb * shade, in.color.a);
 }
-
@@ -0,0 +1,135 @@
+//! Compares the packed `Cells` storage against the previous `Vec<Cell>`
+//! layout, running the same update on both, run with `cargo bench`.
+//!
+//! The previous layout takes 3 bytes per cell, `Cells` takes 3 bytes too
+//! but also keeps the face counts, as the states and the byte neighbour
+//! counts of radius 1 rules are separate buffers.
+use cellular_automata_core::automaton_grid::AutomatonGrid;
+use cellular_automata_core::cell::{CellState, Cells};
+use cellular_automata_core::initial_condition::InitialShape;
+use cellular_automata_core::rule::Rule;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use glam::UVec3;
+
+const SIZE: u32 = 128;
+
+fn random_grid() -> AutomatonGrid {
+    let mut grid = AutomatonGrid::default();
+    grid.seed = 0;
+    grid.initial_condition.shape = InitialShape::RandomFill;
+    grid.initial_condition.density = 0.2;
+    grid.set_size(UVec3::splat(SIZE));
+    grid
+}
+
+fn neighbour_indexes(grid: &AutomatonGrid, idx: usize) -> impl Iterator<Item = usize> + '_ {
+    let pos = grid.idx_to_pos(idx);
+    grid.rule
+        .get_neighbour_iter()
+        .iter()
+        .map(move |dir| grid.pos_to_idx(grid.wrap(pos - *dir)))
+}
+
+/// The cell the grid stored before `Cells`, the state enum next to a byte
+/// neighbour count.
+#[derive(Debug, Clone, Copy)]
+struct Cell {
+    state: CellState,
+    neighbours: u8,
+}
+
+fn next_state(rule: &Rule, state: CellState, neighbours: u16) -> CellState {
+    match state {
+        CellState::Empty => rule.apply_birth_rule(neighbours),
+        CellState::Alive => rule.apply_survival_rule(neighbours),
+        CellState::Dying(state) => rule.apply_dying_rule(state),
+    }
+}
+
+fn step_unpacked(grid: &AutomatonGrid, cells: &mut [Cell]) {
+    let mut changes = vec![];
+    for (idx, cell) in cells.iter_mut().enumerate() {
+        let state = next_state(&grid.rule, cell.state, cell.neighbours.into());
+        match (cell.state, state) {
+            (CellState::Empty, CellState::Alive) => changes.push((idx, true)),
+            (CellState::Alive, CellState::Dying(_) | CellState::Empty) => {
+                changes.push((idx, false))
+            }
+            _ => {}
+        }
+        cell.state = state;
+    }
+    for (idx, increase) in changes {
+        for viewer in neighbour_indexes(grid, idx) {
+            let neighbours = &mut cells[viewer].neighbours;
+            if increase {
+                *neighbours += 1;
+            } else {
+                *neighbours = neighbours.saturating_sub(1);
+            }
+        }
+    }
+}
+
+fn step_packed(grid: &AutomatonGrid, cells: &mut Cells) {
+    let mut changes = vec![];
+    for idx in 0..cells.len() {
+        let cell = cells.get(idx);
+        let state = next_state(&grid.rule, cell.state, cell.neighbours);
+        match (cell.state, state) {
+            (CellState::Empty, CellState::Alive) => changes.push((idx, true)),
+            (CellState::Alive, CellState::Dying(_) | CellState::Empty) => {
+                changes.push((idx, false))
+            }
+            _ => {}
+        }
+        cells.set_state(idx, state);
+    }
+    for (idx, increase) in changes {
+        for viewer in neighbour_indexes(grid, idx) {
+            if increase {
+                cells.increase_neighbours(viewer);
+            } else {
+                cells.decrease_neighbours(viewer);
+            }
+        }
+    }
+}
+
+fn storage(c: &mut Criterion) {
+    let grid = random_grid();
+    let unpacked: Vec<Cell> = grid
+        .cells
+        .iter()
+        .map(|cell| Cell {
+            state: cell.state,
+            neighbours: cell.neighbours as u8,
+        })
+        .collect();
+    assert_eq!(std::mem::size_of::<Cell>(), 3);
+    let packed = grid.cells.clone();
+
+    let mut group = c.benchmark_group(format!("step {SIZE}³"));
+    group.sample_size(10);
+    group.bench_function("Vec<Cell>", |b| {
+        b.iter_batched_ref(
+            || unpacked.clone(),
+            |cells| step_unpacked(&grid, cells),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("Cells", |b| {
+        b.iter_batched_ref(
+            || packed.clone(),
+            |cells| step_packed(&grid, cells),
+            BatchSize::LargeInput,
+        )
+    });
+    group.bench_function("AutomatonGrid::update", |b| {
+        b.iter_batched_ref(random_grid, |grid| grid.update(), BatchSize::LargeInput)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, storage);
+criterion_main!(benches);
@@ -0,0 +1,912 @@
+use crate::cell::{Cell, CellState, Cells};
+use crate::color::{Color, ColorMethod};
+use crate::grid::Grid;
+use crate::initial_condition::InitialCondition;
+use crate::rule::{Indexes, NeighbourMethod, Rule, VONNEUMAN_NEIGHBOURS};
+use glam::{IVec3, UVec3};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::fmt::Display;
+
+#[derive(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+pub struct AutomatonGrid {
+    /// Extent of the grid along each axis.
+    pub size: UVec3,
+    pub cells: Cells,
+    pub rule: Rule,
+    pub color_method: ColorMethod,
+    pub color_1: Color,
+    pub color_2: Color,
+    /// Seed of the initial noise, the same seed and rule always give the same evolution.
+    pub seed: u64,
+    pub initial_condition: InitialCondition,
+    pub boundary_mode: BoundaryMode,
+    /// Step the grid on all cores, only available with the `parallel` feature.
+    pub parallel: bool,
+    /// Steps taken since the last `reset()`.
+    pub generation: u64,
+    pub(crate) active: ActiveCells,
+}
+
+impl AutomatonGrid {
+    pub fn new(
+        size: UVec3,
+        rule: Rule,
+        color_method: ColorMethod,
+        color_1: Color,
+        color_2: Color,
+        seed: u64,
+    ) -> Self {
+        let cells = Cells::new(size.element_product() as usize, rule.get_max_neighbours());
+        let mut grid = Self {
+            size,
+            cells,
+            rule,
+            color_method,
+            color_1,
+            color_2,
+            seed,
+            initial_condition: InitialCondition::default(),
+            boundary_mode: BoundaryMode::default(),
+            parallel: false,
+            generation: 0,
+            active: ActiveCells::default(),
+        };
+        grid.spawn_initial_condition();
+        grid
+    }
+
+    pub fn reset(&mut self) {
+        self.cells = Cells::new(
+            self.size.element_product() as usize,
+            self.rule.get_max_neighbours(),
+        );
+        self.spawn_initial_condition();
+        self.invalidate_active_cells();
+        self.generation = 0;
+    }
+
+    /// Must be called after writing to `cells` directly, so the next
+    /// `update` does not skip any of the changed cells.
+    pub fn invalidate_active_cells(&mut self) {
+        self.active.valid = false;
+    }
+
+    /// Counts the neighbours of every cell again, for after changing the
+    /// neighbour method or radius of `rule` without a reset.
+    pub fn recount_neighbours(&mut self) {
+        self.cells.clear_neighbours(self.rule.get_max_neighbours());
+        for idx in 0..self.cells.len() {
+            if self.cells.state(idx) == CellState::Alive {
+                self.update_neighbours(idx, true);
+            }
+        }
+        self.invalidate_active_cells();
+    }
+
+    pub fn set_size(&mut self, new_size: UVec3) {
+        if self.size != new_size {
+            self.size = new_size;
+            self.reset();
+        }
+    }
+
+    pub fn set_seed(&mut self, new_seed: u64) {
+        if self.seed != new_seed {
+            self.seed = new_seed;
+            self.reset();
+        }
+    }
+
+    pub fn set_boundary_mode(&mut self, new_mode: BoundaryMode) {
+        if self.boundary_mode != new_mode {
+            self.boundary_mode = new_mode;
+            self.reset();
+        }
+    }
+
+    pub fn center(&self) -> IVec3 {
+        self.bounds() / 2
+    }
+
+    pub fn bounds(&self) -> IVec3 {
+        self.size.as_ivec3()
+    }
+
+    fn spawn_initial_condition(&mut self) {
+        let mut rand = ChaCha8Rng::seed_from_u64(self.seed);
+        let positions = self
+            .initial_condition
+            .generate(self.center(), self.size, &mut rand);
+
+        for pos in positions {
+            let pos = match self.boundary_mode {
+                BoundaryMode::Toroidal => self.wrap(pos),
+                _ if self.in_bounds(pos) => pos,
+                _ => continue,
+            };
+            self.spawn(self.pos_to_idx(pos));
+        }
+    }
+
+    /// Makes an empty cell alive and counts it in its neighbours, leaves
+    /// alive or dying cells as they are.
+    pub(crate) fn spawn(&mut self, idx: usize) {
+        if !self.cells.is_occupied(idx) {
+            self.cells.set_state(idx, CellState::Alive);
+            self.update_neighbours(idx, true);
+            self.update_faces(idx);
+        }
+    }
+
+    /// Counts the cell at `idx` in the face counts of the cells next to it if
+    /// it became occupied, or removes it if it became empty. Faces on the
+    /// border of the grid are always visible, whatever the boundary mode.
+    pub(crate) fn update_faces(&mut self, idx: usize) {
+        let pos = self.idx_to_pos(idx);
+        let occupied = self.cells.is_occupied(idx);
+        for dir in VONNEUMAN_NEIGHBOURS {
+            let neighbour = pos + dir;
+            if self.in_bounds(neighbour) {
+                let neighbour_idx = self.pos_to_idx(neighbour);
+                let faces = &mut self.cells.faces[neighbour_idx];
+                if occupied {
+                    *faces += 1;
+                } else {
+                    debug_assert!(*faces > 0, "face count of {neighbour} out of sync");
+                    *faces = faces.saturating_sub(1);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn update_neighbours(&mut self, idx: usize, increase: bool) {
+        let pos = self.idx_to_pos(idx);
+        let neighbourhood = Neighbourhood::new(&self.rule, self.size, self.boundary_mode);
+        let cells = &mut self.cells;
+        let active = &mut self.active;
+        neighbourhood.for_each_viewer(pos, |viewer| {
+            let viewer_idx = neighbourhood.pos_to_idx(viewer);
+            cells.neighbours.update(viewer_idx, increase);
+            if increase {
+                active.insert(viewer_idx);
+            }
+        });
+    }
+
+    pub fn update(&mut self) {
+        self.generation += 1;
+
+        #[cfg(feature = "parallel")]
+        if self.parallel {
+            self.invalidate_active_cells();
+            return self.update_parallel();
+        }
+
+        if self.rule.birth_rule.has(0) {
+            // every empty cell may be born, nothing can be skipped
+            self.invalidate_active_cells();
+            self.update_all();
+        } else {
+            self.update_active();
+        }
+    }
+
+    /// Steps only the cells that can change: the non-empty ones and the empty
+    /// ones with at least one neighbour.
+    fn update_active(&mut self) {
+        if !self.active.valid {
+            self.active.rebuild(&self.cells);
+        }
+        let candidates = std::mem::take(&mut self.active.cells);
+
+        let mut spawns = vec![];
+        let mut deaths = vec![];
+        let mut toggles = vec![];
+        for idx in candidates.iter().map(|idx| *idx as usize) {
+            let state = &mut self.cells.states[idx];
+            let was_occupied = *state != CellState::EMPTY;
+            match step_cell(&self.rule, state, self.cells.neighbours.get(idx)) {
+                Some(Change::Spawn) => spawns.push(idx),
+                Some(Change::Death) => deaths.push(idx),
+                None => {}
+            }
+            if was_occupied != (*state != CellState::EMPTY) {
+                toggles.push(idx);
+            }
+        }
+        // cells that gain their first neighbour are added to the active list here
+        for index in spawns {
+            self.update_neighbours(index, true);
+        }
+        for index in deaths {
+            self.update_neighbours(index, false);
+        }
+        for index in toggles {
+            self.update_faces(index);
+        }
+
+        for idx in candidates {
+            if self.cells.could_change(idx as usize) {
+                self.active.cells.push(idx);
+            } else {
+                self.active.unflag(idx as usize);
+            }
+        }
+    }
+
+    fn update_all(&mut self) {
+        let mut spawns = vec![];
+        let mut deaths = vec![];
+        let mut toggles = vec![];
+
+        let cells = &mut self.cells;
+        for (idx, state) in cells.states.iter_mut().enumerate() {
+            let was_occupied = *state != CellState::EMPTY;
+            match step_cell(&self.rule, state, cells.neighbours.get(idx)) {
+                Some(Change::Spawn) => spawns.push(idx),
+                Some(Change::Death) => deaths.push(idx),
+                None => {}
+            }
+            if was_occupied != (*state != CellState::EMPTY) {
+                toggles.push(idx);
+            }
+        }
+        for index in spawns {
+            self.update_neighbours(index, true);
+        }
+        for index in deaths {
+            self.update_neighbours(index, false);
+        }
+        for index in toggles {
+            self.update_faces(index);
+        }
+    }
+
+    pub fn idx_to_pos(&self, idx: usize) -> IVec3 {
+        let (width, height) = (self.size.x as usize, self.size.y as usize);
+        IVec3::new(
+            (idx % width) as i32,
+            (idx / width % height) as i32,
+            (idx / width / height) as i32,
+        )
+    }
+
+    pub fn pos_to_idx(&self, pos: IVec3) -> usize {
+        let x = pos.x as usize;
+        let y = pos.y as usize;
+        let z = pos.z as usize;
+        let (width, height) = (self.size.x as usize, self.size.y as usize);
+        x + y * width + z * width * height
+    }
+
+    pub fn wrap(&self, pos: IVec3) -> IVec3 {
+        // this causes positions that would go outside the grid
+        // to be considered as positions at the other end
+        pos.rem_euclid(self.bounds())
+    }
+
+    pub fn in_bounds(&self, pos: IVec3) -> bool {
+        pos.cmpge(IVec3::ZERO).all() && pos.cmplt(self.bounds()).all()
+    }
+
+    /// Maps any position to the cell the boundary mode makes it refer to,
+    /// `None` if it has no cell.
+    pub fn resolve(&self, pos: IVec3) -> Option<IVec3> {
+        let bounds = self.bounds();
+        Some(IVec3::new(
+            self.boundary_mode.resolve_axis(pos.x, bounds.x)?,
+            self.boundary_mode.resolve_axis(pos.y, bounds.y)?,
+            self.boundary_mode.resolve_axis(pos.z, bounds.z)?,
+        ))
+    }
+
+    pub fn get_color_by_idx(&self, idx: usize) -> Color {
+        self.color_method
+            .get_color(self, self.idx_to_pos(idx), self.cells.get(idx))
+    }
+
+    pub fn set_example(&mut self, example: Example) {
+        self.rule = example.rule;
+        self.color_method = example.color_method;
+        self.color_1 = example.color_1;
+        self.color_2 = example.color_2;
+        self.reset();
+    }
+}
+impl Default for AutomatonGrid {
+    fn default() -> Self {
+        Self::new(
+            UVec3::splat(64),
+            Rule {
+                survival_rule: Indexes::new(&[2, 6, 9]),
+                birth_rule: Indexes::new(&[4, 6, 8, 9, 10]),
+                states: 10,
+                neighbour_method: NeighbourMethod::Moore,
+                neighbour_radius: 1,
+            },
+            ColorMethod::DistToCenter,
+            Color::srgb(1., 1., 0.),
+            Color::srgb(1., 0., 0.),
+            rand::random(),
+        )
+    }
+}
+
+impl Grid for AutomatonGrid {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn update(&mut self) {
+        self.update();
+    }
+
+    fn reset(&mut self) {
+        self.reset();
+    }
+
+    fn cell(&self, pos: IVec3) -> Cell {
+        if self.in_bounds(pos) {
+            self.cells.get(self.pos_to_idx(pos))
+        } else {
+            Cell::default()
+        }
+    }
+
+    fn occupied(&self) -> Box<dyn Iterator<Item = (IVec3, Cell)> + '_> {
+        Box::new(
+            (0..self.cells.len())
+                .filter(|idx| self.cells.is_occupied(*idx))
+                .map(|idx| (self.idx_to_pos(idx), self.cells.get(idx))),
+        )
+    }
+
+    /// Reads the face count kept by the update instead of the six neighbours.
+    fn is_enclosed(&self, pos: IVec3) -> bool {
+        self.in_bounds(pos) && self.cells.is_enclosed(self.pos_to_idx(pos))
+    }
+
+    fn bounding_box(&self) -> (IVec3, IVec3) {
+        (IVec3::ZERO, self.bounds())
+    }
+
+    fn color_method(&self) -> &ColorMethod {
+        &self.color_method
+    }
+
+    fn colors(&self) -> (Color, Color) {
+        (self.color_1, self.color_2)
+    }
+}
+
+/// Cells that may change on the next step, kept in sync by `update_neighbours`.
+/// Takes 4 bytes per listed cell and a bit per cell of the grid.
+#[derive(Debug, Default)]
+pub(crate) struct ActiveCells {
+    /// Indexes of the listed cells, grids stay far below `u32::MAX` cells.
+    cells: Vec<u32>,
+    /// Bitset of the listed cells, so none is listed twice.
+    flags: Vec<u64>,
+    valid: bool,
+}
+impl ActiveCells {
+    fn rebuild(&mut self, cells: &Cells) {
+        self.flags = vec![0; cells.len().div_ceil(64)];
+        self.cells.clear();
+        for idx in 0..cells.len() {
+            if cells.could_change(idx) {
+                self.flags[idx / 64] |= 1 << (idx % 64);
+                self.cells.push(idx as u32);
+            }
+        }
+        self.valid = true;
+    }
+
+    fn contains(&self, idx: usize) -> bool {
+        self.flags[idx / 64] & 1 << (idx % 64) != 0
+    }
+
+    fn insert(&mut self, idx: usize) {
+        if self.valid && !self.contains(idx) {
+            self.flags[idx / 64] |= 1 << (idx % 64);
+            self.cells.push(idx as u32);
+        }
+    }
+
+    fn unflag(&mut self, idx: usize) {
+        self.flags[idx / 64] &= !(1 << (idx % 64));
+    }
+}
+
+pub(crate) enum Change {
+    Spawn,
+    Death,
+}
+
+/// Applies the rule to a single packed cell state, its neighbour counts are
+/// left untouched.
+pub(crate) fn step_cell(rule: &Rule, state: &mut u8, neighbours: u16) -> Option<Change> {
+    let (next, change) = match CellState::from(*state) {
+        CellState::Empty => {
+            let next = rule.apply_birth_rule(neighbours);
+            (next, (next == CellState::Alive).then_some(Change::Spawn))
+        }
+        CellState::Alive => {
+            let next = rule.apply_survival_rule(neighbours);
+            (next, (next != CellState::Alive).then_some(Change::Death))
+        }
+        CellState::Dying(dying) => (rule.apply_dying_rule(dying), None),
+    };
+    *state = next.into();
+    change
+}
+
+/// The neighbour offsets of a rule together with the grid shape and boundary,
+/// enough to know which cells see a given cell without borrowing the cells.
+pub(crate) struct Neighbourhood<'a> {
+    offsets: &'a [IVec3],
+    pub reach: i32,
+    size: UVec3,
+    boundary_mode: BoundaryMode,
+}
+impl<'a> Neighbourhood<'a> {
+    pub fn new(rule: &'a Rule, size: UVec3, boundary_mode: BoundaryMode) -> Self {
+        Self {
+            offsets: rule.get_neighbour_iter(),
+            reach: rule.get_neighbour_reach(),
+            size,
+            boundary_mode,
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    pub fn idx_to_pos(&self, idx: usize) -> IVec3 {
+        let (width, height) = (self.size.x as usize, self.size.y as usize);
+        IVec3::new(
+            (idx % width) as i32,
+            (idx / width % height) as i32,
+            (idx / width / height) as i32,
+        )
+    }
+
+    pub fn pos_to_idx(&self, pos: IVec3) -> usize {
+        let (width, height) = (self.size.x as usize, self.size.y as usize);
+        pos.x as usize + pos.y as usize * width + pos.z as usize * width * height
+    }
+
+    /// Calls `f` for every cell that counts `pos` as a neighbour, once per
+    /// offset through which it sees it.
+    pub fn for_each_viewer(&self, pos: IVec3, mut f: impl FnMut(IVec3)) {
+        let bounds = self.size.as_ivec3();
+
+        // cells this far from every face are never seen through the boundary,
+        // the extra layer covers reflections of the cells next to the face
+        let margin = IVec3::splat(self.reach + 1);
+        if pos.cmpge(margin).all() && pos.cmplt(bounds - margin).all() {
+            for dir in self.offsets {
+                f(pos - *dir);
+            }
+            return;
+        }
+        // near a face the boundary may map several positions outside the grid
+        // onto this cell, every cell that sees one of them must be updated
+        for image in self.boundary_images(pos) {
+            for dir in self.offsets {
+                let viewer = image - *dir;
+                if viewer.cmpge(IVec3::ZERO).all() && viewer.cmplt(bounds).all() {
+                    f(viewer);
+                }
+            }
+        }
+    }
+
+    fn boundary_images(&self, pos: IVec3) -> Vec<IVec3> {
+        let reach = self.reach;
+        let axis_images = |coord: i32, bounds: u32| {
+            let bounds = bounds as i32;
+            let mut images = vec![coord];
+            for outside in (-reach..0).chain(bounds..bounds + reach) {
+                if self.boundary_mode.resolve_axis(outside, bounds) == Some(coord) {
+                    images.push(outside);
+                }
+            }
+            images
+        };
+
+        let xs = axis_images(pos.x, self.size.x);
+        let ys = axis_images(pos.y, self.size.y);
+        let zs = axis_images(pos.z, self.size.z);
+        let mut images = vec![];
+        for z in &zs {
+            for y in &ys {
+                for x in &xs {
+                    images.push(IVec3::new(*x, *y, *z));
+                }
+            }
+        }
+        images
+    }
+}
+
+/// What the cells on the faces of the grid see beyond them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BoundaryMode {
+    /// Opposite faces are connected.
+    #[default]
+    Toroidal,
+    /// Everything outside the grid is empty.
+    Dead,
+    /// The grid is mirrored across its faces.
+    Reflective,
+    /// Positions outside the grid repeat the cell on the face.
+    Clamped,
+}
+impl BoundaryMode {
+    pub fn resolve_axis(&self, coord: i32, bounds: i32) -> Option<i32> {
+        if (0..bounds).contains(&coord) {
+            return Some(coord);
+        }
+        match self {
+            BoundaryMode::Toroidal => Some(coord.rem_euclid(bounds)),
+            BoundaryMode::Dead => None,
+            BoundaryMode::Reflective if bounds == 1 => Some(0),
+            BoundaryMode::Reflective => {
+                // the face cell is the mirror, so -1 maps to 1
+                let period = 2 * (bounds - 1);
+                let coord = coord.rem_euclid(period);
+                Some(if coord < bounds {
+                    coord
+                } else {
+                    period - coord
+                })
+            }
+            BoundaryMode::Clamped => Some(coord.clamp(0, bounds - 1)),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+pub struct Examples(pub Vec<Example>);
+impl Examples {
+    pub fn add(&mut self, example: Example) {
+        self.0.push(example);
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Example {
+    pub name: String,
+    pub rule: Rule,
+    pub color_method: ColorMethod,
+    pub color_1: Color,
+    pub color_2: Color,
+}
+impl Display for Example {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::initial_condition::InitialShape;
+
+    #[test]
+    fn grid_idx_to_pos() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(5));
+
+        assert_eq!(grid.idx_to_pos(0), IVec3::new(0, 0, 0));
+        assert_eq!(grid.idx_to_pos(10), IVec3::new(0, 2, 0));
+        assert_eq!(grid.idx_to_pos(124), IVec3::new(4, 4, 4));
+        assert_eq!(grid.idx_to_pos(34), IVec3::new(4, 1, 1));
+
+        grid.set_size(UVec3::new(4, 3, 2));
+
+        assert_eq!(grid.idx_to_pos(5), IVec3::new(1, 1, 0));
+        assert_eq!(grid.idx_to_pos(13), IVec3::new(1, 0, 1));
+        assert_eq!(grid.idx_to_pos(23), IVec3::new(3, 2, 1));
+    }
+
+    #[test]
+    fn grid_pos_to_idx() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(5));
+
+        assert_eq!(grid.pos_to_idx(IVec3::new(0, 0, 0)), 0);
+        assert_eq!(grid.pos_to_idx(IVec3::new(0, 2, 0)), 10);
+        assert_eq!(grid.pos_to_idx(IVec3::new(4, 4, 4)), 124);
+        assert_eq!(grid.pos_to_idx(IVec3::new(4, 1, 1)), 34);
+
+        grid.set_size(UVec3::new(4, 3, 2));
+
+        assert_eq!(grid.pos_to_idx(IVec3::new(1, 1, 0)), 5);
+        assert_eq!(grid.pos_to_idx(IVec3::new(1, 0, 1)), 13);
+        assert_eq!(grid.pos_to_idx(IVec3::new(3, 2, 1)), 23);
+        assert_eq!(grid.cells.len(), 24);
+    }
+
+    #[test]
+    fn wrap() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(5));
+
+        assert_eq!(grid.wrap(IVec3::new(-1, 1, 2)), IVec3::new(4, 1, 2));
+        assert_eq!(grid.wrap(IVec3::new(4, 4, 5)), IVec3::new(4, 4, 0));
+        assert_eq!(grid.wrap(IVec3::new(4, 1, 1)), IVec3::new(4, 1, 1));
+
+        grid.set_size(UVec3::new(4, 3, 2));
+
+        assert_eq!(grid.wrap(IVec3::new(-1, 3, 2)), IVec3::new(3, 0, 0));
+        assert_eq!(grid.wrap(IVec3::new(4, -1, -1)), IVec3::new(0, 2, 1));
+        assert_eq!(grid.center(), IVec3::new(2, 1, 1));
+    }
+
+    #[test]
+    fn resolve_toroidal() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(5));
+
+        assert_eq!(
+            grid.resolve(IVec3::new(-1, 1, 2)),
+            Some(IVec3::new(4, 1, 2))
+        );
+        assert_eq!(grid.resolve(IVec3::new(4, 4, 5)), Some(IVec3::new(4, 4, 0)));
+    }
+
+    #[test]
+    fn resolve_dead() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(5));
+        grid.set_boundary_mode(BoundaryMode::Dead);
+
+        assert_eq!(grid.resolve(IVec3::new(-1, 1, 2)), None);
+        assert_eq!(grid.resolve(IVec3::new(4, 4, 5)), None);
+        assert_eq!(grid.resolve(IVec3::new(4, 1, 1)), Some(IVec3::new(4, 1, 1)));
+    }
+
+    #[test]
+    fn resolve_reflective() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(5));
+        grid.set_boundary_mode(BoundaryMode::Reflective);
+
+        assert_eq!(
+            grid.resolve(IVec3::new(-1, 1, 2)),
+            Some(IVec3::new(1, 1, 2))
+        );
+        assert_eq!(grid.resolve(IVec3::new(4, 4, 5)), Some(IVec3::new(4, 4, 3)));
+        assert_eq!(
+            grid.resolve(IVec3::new(-2, 6, 0)),
+            Some(IVec3::new(2, 2, 0))
+        );
+    }
+
+    #[test]
+    fn resolve_clamped() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(5));
+        grid.set_boundary_mode(BoundaryMode::Clamped);
+
+        assert_eq!(
+            grid.resolve(IVec3::new(-1, 1, 2)),
+            Some(IVec3::new(0, 1, 2))
+        );
+        assert_eq!(grid.resolve(IVec3::new(4, 4, 5)), Some(IVec3::new(4, 4, 4)));
+        assert_eq!(
+            grid.resolve(IVec3::new(-2, 6, 0)),
+            Some(IVec3::new(0, 4, 0))
+        );
+    }
+
+    fn count_neighbours(grid: &AutomatonGrid, idx: usize) -> u16 {
+        let pos = grid.idx_to_pos(idx);
+        grid.rule
+            .get_neighbour_iter()
+            .iter()
+            .filter_map(|dir| grid.resolve(pos + *dir))
+            .filter(|pos| grid.cells.state(grid.pos_to_idx(*pos)) == CellState::Alive)
+            .count() as u16
+    }
+
+    fn count_faces(grid: &AutomatonGrid, idx: usize) -> u8 {
+        let pos = grid.idx_to_pos(idx);
+        VONNEUMAN_NEIGHBOURS
+            .iter()
+            .filter(|dir| grid.in_bounds(pos + **dir))
+            .filter(|dir| grid.cells.is_occupied(grid.pos_to_idx(pos + **dir)))
+            .count() as u8
+    }
+
+    #[test]
+    fn recount_after_neighbourhood_change() {
+        let mut grid = AutomatonGrid {
+            seed: 2,
+            ..Default::default()
+        };
+        grid.set_size(UVec3::splat(16));
+        for _ in 0..3 {
+            grid.update();
+        }
+        grid.rule = "5,6/1-3/15/N".parse().unwrap();
+        grid.recount_neighbours();
+        for _ in 0..6 {
+            for idx in 0..grid.cells.len() {
+                assert_eq!(grid.cells.neighbours(idx), count_neighbours(&grid, idx));
+                assert_eq!(grid.cells.faces(idx), count_faces(&grid, idx));
+            }
+            grid.update();
+        }
+    }
+
+    #[test]
+    fn boundary_modes_keep_neighbour_counts() {
+        for mode in [
+            BoundaryMode::Toroidal,
+            BoundaryMode::Dead,
+            BoundaryMode::Reflective,
+            BoundaryMode::Clamped,
+        ] {
+            for (neighbour_method, radius) in [
+                (NeighbourMethod::Moore, 1),
+                (NeighbourMethod::VonNeumann, 1),
+                (NeighbourMethod::Moore, 2),
+                (NeighbourMethod::VonNeumann, 3),
+                (NeighbourMethod::FaceCenteredCubic, 1),
+                (
+                    NeighbourMethod::custom([
+                        IVec3::new(1, 0, 0),
+                        IVec3::new(0, 1, 0),
+                        IVec3::new(2, 1, -1),
+                    ]),
+                    1,
+                ),
+            ] {
+                let mut grid = AutomatonGrid::default();
+                grid.rule.neighbour_method = neighbour_method.clone();
+                grid.rule.neighbour_radius = radius;
+                grid.initial_condition.shape = InitialShape::RandomFill;
+                grid.initial_condition.density = 0.3;
+                grid.boundary_mode = mode;
+                grid.seed = 3;
+                grid.set_size(UVec3::new(8, 6, 4));
+
+                for _ in 0..10 {
+                    for idx in 0..grid.cells.len() {
+                        assert_eq!(
+                            grid.cells.neighbours(idx),
+                            count_neighbours(&grid, idx),
+                            "{mode:?} {neighbour_method:?} {radius} at {}",
+                            grid.idx_to_pos(idx)
+                        );
+                    }
+                    grid.update();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn active_update_matches_full_scan() {
+        for (rule, mode) in [
+            ("5,6/1-3/15/N", BoundaryMode::Toroidal),
+            ("2,6,9/4,6,8-10/10/M", BoundaryMode::Dead),
+            ("4/4/5/M", BoundaryMode::Reflective),
+            ("9-26/5-7,12,13,15/20/M", BoundaryMode::Clamped),
+            ("0-3/0,2/4/N", BoundaryMode::Toroidal),
+        ] {
+            let mut full = AutomatonGrid {
+                rule: rule.parse().unwrap(),
+                boundary_mode: mode,
+                seed: 5,
+                ..Default::default()
+            };
+            full.set_size(UVec3::new(24, 20, 16));
+            let mut active = AutomatonGrid {
+                rule: full.rule.clone(),
+                boundary_mode: mode,
+                seed: 5,
+                ..Default::default()
+            };
+            active.set_size(full.size);
+
+            for step in 0..40 {
+                full.update_all();
+                active.update();
+                assert!(full.cells == active.cells, "{rule} diverged at step {step}");
+            }
+        }
+    }
+
+    #[test]
+    fn active_cells_skip_dead_regions() {
+        let mut grid = AutomatonGrid {
+            rule: "5,6/1-3/15/N".parse().unwrap(),
+            seed: 1,
+            ..Default::default()
+        };
+        grid.initial_condition.shape = InitialShape::SingleCell;
+        grid.reset();
+        grid.update();
+
+        assert!(grid.active.cells.len() < 100);
+        for idx in 0..grid.cells.len() {
+            assert_eq!(grid.active.contains(idx), grid.cells.could_change(idx));
+        }
+    }
+
+    #[test]
+    fn face_counts_match_neighbours() {
+        for (rule, mode) in [
+            ("9-26/5-7,12-13,15/5/M", BoundaryMode::Toroidal),
+            ("4/4/2/M", BoundaryMode::Dead),
+        ] {
+            let mut grid = AutomatonGrid {
+                rule: rule.parse().unwrap(),
+                boundary_mode: mode,
+                seed: 5,
+                ..Default::default()
+            };
+            grid.initial_condition.shape = InitialShape::RandomFill;
+            grid.initial_condition.density = 0.5;
+            grid.set_size(UVec3::splat(12));
+
+            for _ in 0..10 {
+                grid.update();
+                for idx in 0..grid.cells.len() {
+                    let pos = grid.idx_to_pos(idx);
+                    let enclosed = VONNEUMAN_NEIGHBOURS
+                        .iter()
+                        .all(|dir| grid.state(pos + *dir) != CellState::Empty);
+                    assert_eq!(grid.is_enclosed(pos), enclosed, "{rule} at {pos}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_same_evolution() {
+        let mut grid_a = AutomatonGrid::default();
+        grid_a.set_size(UVec3::splat(32));
+        grid_a.set_seed(42);
+        let mut grid_b = AutomatonGrid::default();
+        grid_b.set_size(UVec3::splat(32));
+        grid_b.set_seed(42);
+
+        assert_eq!(grid_a.cells, grid_b.cells);
+        for _ in 0..20 {
+            grid_a.update();
+            grid_b.update();
+            assert_eq!(grid_a.cells, grid_b.cells);
+        }
+    }
+
+    #[test]
+    fn reset_reproduces_seed() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(32));
+        grid.set_seed(7);
+        let initial = grid.cells.clone();
+
+        for _ in 0..10 {
+            grid.update();
+        }
+        grid.reset();
+        assert_eq!(grid.cells, initial);
+    }
+
+    #[test]
+    fn different_seed_different_noise() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(32));
+        grid.set_seed(1);
+        let first = grid.cells.clone();
+
+        grid.set_seed(2);
+        assert_ne!(grid.cells, first);
+    }
+}
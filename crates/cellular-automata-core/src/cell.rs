@@ -0,0 +1,267 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub state: CellState,
+    pub neighbours: u16,
+}
+
+impl Cell {
+    pub fn increase_neighbours(&mut self) {
+        self.neighbours += 1;
+    }
+    pub fn decrease_neighbours(&mut self) {
+        if self.neighbours > 0 {
+            self.neighbours -= 1;
+        }
+    }
+    /// Whether the cell may change state on the next step, assuming the
+    /// rule does not give birth to cells without neighbours.
+    pub fn could_change(&self) -> bool {
+        self.state != CellState::Empty || self.neighbours > 0
+    }
+    pub fn get_value(&self, total_states: u8) -> u8 {
+        match self.state {
+            CellState::Empty => 0,
+            CellState::Alive => total_states - 1,
+            CellState::Dying(state) => state,
+        }
+    }
+}
+impl Default for Cell {
+    fn default() -> Self {
+        Self {
+            state: CellState::Empty,
+            neighbours: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CellState {
+    Empty,
+    Alive,
+    Dying(u8),
+}
+impl CellState {
+    pub(crate) const EMPTY: u8 = 0;
+    // dying states go from 1 up to 253, as rules have at most 255 states
+    const ALIVE: u8 = u8::MAX;
+}
+impl From<CellState> for u8 {
+    fn from(state: CellState) -> Self {
+        match state {
+            CellState::Empty => CellState::EMPTY,
+            CellState::Alive => CellState::ALIVE,
+            CellState::Dying(state) => state,
+        }
+    }
+}
+impl From<u8> for CellState {
+    fn from(state: u8) -> Self {
+        match state {
+            CellState::EMPTY => CellState::Empty,
+            CellState::ALIVE => CellState::Alive,
+            state => CellState::Dying(state),
+        }
+    }
+}
+
+/// The cells of a grid stored as parallel buffers, a byte per state, the
+/// neighbour counts and the occupied face neighbour counts apart, which takes
+/// 3 bytes per cell, 4 when the neighbourhood has more than 255 cells, and
+/// keeps each pass of the update reading a single dense buffer.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Cells {
+    pub(crate) states: Vec<u8>,
+    pub(crate) neighbours: NeighbourCounts,
+    /// How many of the six face neighbours are alive or dying, only kept up
+    /// to date by `AutomatonGrid`.
+    pub(crate) faces: Vec<u8>,
+}
+impl Cells {
+    /// `len` empty cells without neighbours, with counts wide enough for
+    /// `max_neighbours`.
+    pub fn new(len: usize, max_neighbours: u16) -> Self {
+        Self {
+            states: vec![CellState::EMPTY; len],
+            neighbours: NeighbourCounts::new(len, max_neighbours),
+            faces: vec![0; len],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+
+    /// Unpacked copy of the cell at `idx`.
+    pub fn get(&self, idx: usize) -> Cell {
+        Cell {
+            state: self.state(idx),
+            neighbours: self.neighbours.get(idx),
+        }
+    }
+
+    pub fn state(&self, idx: usize) -> CellState {
+        self.states[idx].into()
+    }
+
+    pub fn set_state(&mut self, idx: usize, state: CellState) {
+        self.states[idx] = state.into();
+    }
+
+    /// Whether the cell at `idx` is alive or dying, cheaper than `state`.
+    pub fn is_occupied(&self, idx: usize) -> bool {
+        self.states[idx] != CellState::EMPTY
+    }
+
+    pub fn neighbours(&self, idx: usize) -> u16 {
+        self.neighbours.get(idx)
+    }
+
+    pub fn increase_neighbours(&mut self, idx: usize) {
+        self.neighbours.update(idx, true);
+    }
+
+    pub fn decrease_neighbours(&mut self, idx: usize) {
+        self.neighbours.update(idx, false);
+    }
+
+    /// Sets every neighbour count to 0, with counts wide enough for
+    /// `max_neighbours`.
+    pub fn clear_neighbours(&mut self, max_neighbours: u16) {
+        self.neighbours = NeighbourCounts::new(self.len(), max_neighbours);
+    }
+
+    /// Number of alive or dying cells sharing a face with the cell at `idx`.
+    pub fn faces(&self, idx: usize) -> u8 {
+        self.faces[idx]
+    }
+
+    /// Whether all six face neighbours are occupied, so the cell cannot be seen.
+    pub fn is_enclosed(&self, idx: usize) -> bool {
+        self.faces[idx] == 6
+    }
+
+    /// Same as `Cell::could_change`.
+    pub fn could_change(&self, idx: usize) -> bool {
+        self.is_occupied(idx) || self.neighbours.get(idx) > 0
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Cell> + '_ {
+        (0..self.len()).map(|idx| self.get(idx))
+    }
+}
+
+/// Neighbour counts of the cells, a byte each unless the neighbourhood has
+/// more than 255 cells.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum NeighbourCounts {
+    Narrow(Vec<u8>),
+    Wide(Vec<u16>),
+}
+impl NeighbourCounts {
+    fn new(len: usize, max_neighbours: u16) -> Self {
+        if max_neighbours <= u8::MAX as u16 {
+            Self::Narrow(vec![0; len])
+        } else {
+            Self::Wide(vec![0; len])
+        }
+    }
+
+    pub(crate) fn get(&self, idx: usize) -> u16 {
+        match self {
+            Self::Narrow(counts) => counts[idx] as u16,
+            Self::Wide(counts) => counts[idx],
+        }
+    }
+
+    pub(crate) fn update(&mut self, idx: usize, increase: bool) {
+        match self {
+            Self::Narrow(counts) => counts[idx].update(increase),
+            Self::Wide(counts) => counts[idx].update(increase),
+        }
+    }
+}
+impl Default for NeighbourCounts {
+    fn default() -> Self {
+        Self::Narrow(vec![])
+    }
+}
+
+/// Integer type of a neighbour count buffer.
+pub(crate) trait Count: Copy + Send + Sync {
+    /// Counts one more neighbour, or one less without going below 0.
+    fn update(&mut self, increase: bool);
+}
+impl Count for u8 {
+    fn update(&mut self, increase: bool) {
+        if increase {
+            *self += 1;
+        } else {
+            *self = self.saturating_sub(1);
+        }
+    }
+}
+impl Count for u16 {
+    fn update(&mut self, increase: bool) {
+        if increase {
+            *self += 1;
+        } else {
+            *self = self.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn states_round_trip_through_bytes() {
+        let states = [
+            CellState::Empty,
+            CellState::Alive,
+            CellState::Dying(1),
+            CellState::Dying(253),
+        ];
+        for state in states {
+            assert_eq!(CellState::from(u8::from(state)), state);
+        }
+    }
+
+    #[test]
+    fn packed_cells_match_unpacked() {
+        let mut cells = Cells::new(4, 26);
+        cells.set_state(1, CellState::Alive);
+        cells.set_state(2, CellState::Dying(3));
+        cells.increase_neighbours(3);
+        cells.decrease_neighbours(0);
+
+        assert_eq!(cells.get(1).get_value(5), 4);
+        assert_eq!(cells.get(2).get_value(5), 3);
+        assert_eq!(cells.neighbours(0), 0);
+        let could_change: Vec<bool> = cells.iter().map(|cell| cell.could_change()).collect();
+        assert_eq!(could_change, [false, true, true, true]);
+    }
+
+    #[test]
+    fn counts_widen_past_255_neighbours() {
+        let mut cells = Cells::new(2, 255);
+        assert!(matches!(cells.neighbours, NeighbourCounts::Narrow(_)));
+        for _ in 0..255 {
+            cells.increase_neighbours(0);
+        }
+        assert_eq!(cells.neighbours(0), 255);
+
+        cells.clear_neighbours(342);
+        assert!(matches!(cells.neighbours, NeighbourCounts::Wide(_)));
+        for _ in 0..342 {
+            cells.increase_neighbours(1);
+        }
+        assert_eq!(cells.neighbours(1), 342);
+        assert_eq!(cells.neighbours(0), 0);
+    }
+}
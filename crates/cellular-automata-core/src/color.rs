@@ -0,0 +1,130 @@
+use crate::cell::Cell;
+use crate::grid::Grid;
+use glam::IVec3;
+
+/// Non-linear sRGB color, the engine agnostic counterpart of `bevy::color::Color`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Color {
+    pub red: f32,
+    pub green: f32,
+    pub blue: f32,
+    pub alpha: f32,
+}
+impl Color {
+    pub const fn srgb(red: f32, green: f32, blue: f32) -> Self {
+        Self {
+            red,
+            green,
+            blue,
+            alpha: 1.0,
+        }
+    }
+
+    pub fn from_linear(linear: [f32; 4]) -> Self {
+        Self {
+            red: linear_to_srgb(linear[0]),
+            green: linear_to_srgb(linear[1]),
+            blue: linear_to_srgb(linear[2]),
+            alpha: linear[3],
+        }
+    }
+
+    pub fn to_linear(self) -> [f32; 4] {
+        [
+            srgb_to_linear(self.red),
+            srgb_to_linear(self.green),
+            srgb_to_linear(self.blue),
+            self.alpha,
+        ]
+    }
+
+    pub fn to_array(self) -> [f32; 4] {
+        [self.red, self.green, self.blue, self.alpha]
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+#[cfg(feature = "bevy")]
+impl From<Color> for bevy_color::Color {
+    fn from(color: Color) -> Self {
+        bevy_color::Color::srgba(color.red, color.green, color.blue, color.alpha)
+    }
+}
+
+#[cfg(feature = "bevy")]
+impl From<bevy_color::Color> for Color {
+    fn from(color: bevy_color::Color) -> Self {
+        bevy_color::Srgba::from(color).into()
+    }
+}
+
+#[cfg(feature = "bevy")]
+impl From<bevy_color::Srgba> for Color {
+    fn from(color: bevy_color::Srgba) -> Self {
+        Self {
+            red: color.red,
+            green: color.green,
+            blue: color.blue,
+            alpha: color.alpha,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub enum ColorMethod {
+    #[default]
+    DistToCenter,
+    StateLerp,
+    Neighbour,
+}
+impl ColorMethod {
+    /// Color of `cell`, found at `pos` in `grid`.
+    pub fn get_color<G: Grid + ?Sized>(&self, grid: &G, pos: IVec3, cell: Cell) -> Color {
+        let (color_1, color_2) = grid.colors();
+        lerp_color(color_1, color_2, self.get_blend(grid, pos, cell))
+    }
+
+    /// How far the color of `cell` is from color A towards color B, from 0 to 1.
+    pub fn get_blend<G: Grid + ?Sized>(&self, grid: &G, pos: IVec3, cell: Cell) -> f32 {
+        let (min, max) = grid.bounding_box();
+        let cell_pos_centered = pos - grid.center();
+        let dist_to_center =
+            cell_pos_centered.as_vec3().length() / ((max - min).max_element() as f32 / 2.0);
+        let rule = grid.rule();
+        let state = cell.get_value(rule.states);
+        let neighbours = cell.neighbours;
+        let max_neighbours = rule.get_max_neighbours();
+
+        let dt = match self {
+            ColorMethod::StateLerp => state as f32 / (rule.states - 1) as f32,
+            ColorMethod::DistToCenter => dist_to_center,
+            ColorMethod::Neighbour => neighbours as f32 / max_neighbours as f32,
+        };
+        dt.clamp(0.0, 1.0)
+    }
+}
+
+pub(crate) fn lerp_color(color_1: Color, color_2: Color, dt: f32) -> Color {
+    let color_1 = color_1.to_linear();
+    let color_2 = color_2.to_linear();
+    let dt = dt.clamp(0.0, 1.0);
+
+    Color::from_linear(std::array::from_fn(|i| {
+        (1.0 - dt) * color_1[i] + dt * color_2[i]
+    }))
+}
@@ -0,0 +1,53 @@
+use crate::cell::{Cell, CellState};
+use crate::color::{Color, ColorMethod};
+use crate::rule::{Rule, VONNEUMAN_NEIGHBOURS};
+use glam::IVec3;
+
+/// Operations shared by the grid backends, so the same code can step, color
+/// and draw a fixed `AutomatonGrid` or an unbounded `SparseGrid`.
+pub trait Grid {
+    fn rule(&self) -> &Rule;
+
+    /// Advances the automaton one generation.
+    fn update(&mut self);
+
+    /// Clears the grid and spawns the initial condition again.
+    fn reset(&mut self);
+
+    /// The cell at `pos`, an empty cell without neighbours if there is none.
+    fn cell(&self, pos: IVec3) -> Cell;
+
+    /// Positions and copies of every alive or dying cell.
+    fn occupied(&self) -> Box<dyn Iterator<Item = (IVec3, Cell)> + '_>;
+
+    /// Smallest and one past the largest position of the region to show,
+    /// the whole grid for bounded backends.
+    fn bounding_box(&self) -> (IVec3, IVec3);
+
+    fn color_method(&self) -> &ColorMethod;
+
+    /// The two colors the color method blends between.
+    fn colors(&self) -> (Color, Color);
+
+    fn state(&self, pos: IVec3) -> CellState {
+        self.cell(pos).state
+    }
+
+    /// Whether the six cells sharing a face with `pos` are alive or dying,
+    /// so the cell at `pos` cannot be seen from outside.
+    fn is_enclosed(&self, pos: IVec3) -> bool {
+        VONNEUMAN_NEIGHBOURS
+            .iter()
+            .all(|dir| self.state(pos + *dir) != CellState::Empty)
+    }
+
+    fn center(&self) -> IVec3 {
+        let (min, max) = self.bounding_box();
+        (min + max) / 2
+    }
+
+    /// Color of the cell at `pos`, `cell` being what `cell(pos)` returns.
+    fn color(&self, pos: IVec3, cell: Cell) -> Color {
+        self.color_method().get_color(self, pos, cell)
+    }
+}
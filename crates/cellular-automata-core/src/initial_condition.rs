@@ -0,0 +1,182 @@
+use glam::{IVec3, UVec3};
+use rand::Rng;
+
+/// Describes how the grid is populated on `reset()`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InitialCondition {
+    pub shape: InitialShape,
+    /// Half extent of the seeded region around the center, ignored by
+    /// `SingleCell` and `RandomFill`.
+    pub radius: u32,
+    /// Chance of each candidate cell being spawned alive, ignored by `SingleCell`.
+    pub density: f32,
+}
+impl InitialCondition {
+    /// Positions to spawn relative to the grid, they may fall outside of it
+    /// and may repeat, so callers are expected to wrap and deduplicate.
+    pub fn generate(&self, center: IVec3, size: UVec3, rng: &mut impl Rng) -> Vec<IVec3> {
+        let radius = self.radius as i32;
+        let density = self.density.clamp(0.0, 1.0) as f64;
+        let mut positions = vec![];
+
+        match self.shape {
+            InitialShape::NoiseCube => {
+                for offset in cube(radius) {
+                    if rng.gen_bool(density) {
+                        positions.push(center + offset);
+                    }
+                }
+            }
+            InitialShape::NoiseSphere => {
+                for offset in cube(radius) {
+                    if offset.length_squared() <= radius.pow(2) && rng.gen_bool(density) {
+                        positions.push(center + offset);
+                    }
+                }
+            }
+            InitialShape::SingleCell => positions.push(center),
+            InitialShape::HollowShell => {
+                // only the outermost layer of the sphere, one cell thick
+                let inner = (radius - 1).max(0).pow(2);
+                for offset in cube(radius) {
+                    let dist = offset.length_squared();
+                    if dist > inner && dist <= radius.pow(2) && rng.gen_bool(density) {
+                        positions.push(center + offset);
+                    }
+                }
+            }
+            InitialShape::RandomFill => {
+                for z in 0..size.z as i32 {
+                    for y in 0..size.y as i32 {
+                        for x in 0..size.x as i32 {
+                            if rng.gen_bool(density) {
+                                positions.push(IVec3::new(x, y, z));
+                            }
+                        }
+                    }
+                }
+            }
+            InitialShape::SymmetricNoise => {
+                // noise in one octant, mirrored across the three axes
+                for x in 0..=radius {
+                    for y in 0..=radius {
+                        for z in 0..=radius {
+                            if !rng.gen_bool(density) {
+                                continue;
+                            }
+                            for sign in MIRRORS {
+                                positions.push(center + IVec3::new(x, y, z) * sign);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        positions
+    }
+}
+impl Default for InitialCondition {
+    fn default() -> Self {
+        Self {
+            shape: InitialShape::NoiseCube,
+            radius: 6,
+            density: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InitialShape {
+    #[default]
+    NoiseCube,
+    NoiseSphere,
+    SingleCell,
+    HollowShell,
+    RandomFill,
+    SymmetricNoise,
+}
+impl InitialShape {
+    pub fn uses_radius(&self) -> bool {
+        !matches!(self, InitialShape::SingleCell | InitialShape::RandomFill)
+    }
+
+    pub fn uses_density(&self) -> bool {
+        !matches!(self, InitialShape::SingleCell)
+    }
+}
+
+static MIRRORS: [IVec3; 8] = [
+    IVec3::new(1, 1, 1),
+    IVec3::new(-1, 1, 1),
+    IVec3::new(1, -1, 1),
+    IVec3::new(-1, -1, 1),
+    IVec3::new(1, 1, -1),
+    IVec3::new(-1, 1, -1),
+    IVec3::new(1, -1, -1),
+    IVec3::new(-1, -1, -1),
+];
+
+fn cube(radius: i32) -> impl Iterator<Item = IVec3> {
+    (-radius..=radius).flat_map(move |z| {
+        (-radius..=radius).flat_map(move |y| (-radius..=radius).map(move |x| IVec3::new(x, y, z)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::HashSet;
+
+    fn generate(shape: InitialShape, radius: u32, density: f32) -> Vec<IVec3> {
+        let condition = InitialCondition {
+            shape,
+            radius,
+            density,
+        };
+        let mut rng = ChaCha8Rng::seed_from_u64(0);
+        condition.generate(IVec3::splat(8), UVec3::splat(16), &mut rng)
+    }
+
+    #[test]
+    fn single_cell() {
+        let positions = generate(InitialShape::SingleCell, 6, 0.5);
+        assert_eq!(positions, vec![IVec3::splat(8)]);
+    }
+
+    #[test]
+    fn full_density_fills_shapes() {
+        assert_eq!(generate(InitialShape::NoiseCube, 2, 1.0).len(), 125);
+        assert_eq!(
+            generate(InitialShape::RandomFill, 2, 1.0).len(),
+            16usize.pow(3)
+        );
+        assert!(generate(InitialShape::NoiseSphere, 3, 0.0).is_empty());
+    }
+
+    #[test]
+    fn sphere_and_shell_stay_within_radius() {
+        for pos in generate(InitialShape::NoiseSphere, 4, 1.0) {
+            assert!((pos - IVec3::splat(8)).length_squared() <= 16);
+        }
+        for pos in generate(InitialShape::HollowShell, 4, 1.0) {
+            let dist = (pos - IVec3::splat(8)).length_squared();
+            assert!(dist > 9 && dist <= 16);
+        }
+    }
+
+    #[test]
+    fn symmetric_noise_is_mirrored() {
+        let positions: HashSet<IVec3> = generate(InitialShape::SymmetricNoise, 5, 0.3)
+            .into_iter()
+            .collect();
+        assert!(!positions.is_empty());
+        for pos in &positions {
+            let offset = *pos - IVec3::splat(8);
+            for sign in MIRRORS {
+                assert!(positions.contains(&(IVec3::splat(8) + offset * sign)));
+            }
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Headless simulation core of the 3D cellular automaton.
+//!
+//! Nothing in here depends on Bevy, so the automaton can be stepped from tests,
+//! batch jobs or servers. Enable the `bevy` feature to get the `Resource`
+//! derives and the conversions the viewer needs, and the `parallel` feature
+//! to step large grids on all cores.
+
+pub mod automaton_grid;
+pub mod cell;
+pub mod color;
+pub mod grid;
+pub mod initial_condition;
+pub mod mesh;
+#[cfg(feature = "parallel")]
+mod parallel;
+pub mod rule;
+pub mod scale;
+pub mod snapshot;
+pub mod sparse_grid;
+pub mod vox;
+
+pub use glam::IVec3;
@@ -0,0 +1,663 @@
+//! Surface meshes of the occupied cells, for 3D printing and modelling tools.
+//!
+//! Meshes are in cell units with the smallest corner of the bounding box at
+//! the origin, Y up like the grid. Out of bounds positions count as empty, so
+//! toroidal grids are closed at their faces too.
+use crate::cell::CellState;
+use crate::color::Color;
+use crate::grid::Grid;
+use glam::{IVec3, Vec3};
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+/// Outward normal and the two edges of each cube face, with
+/// `u.cross(v) == normal` so the corners go counter-clockwise from outside.
+const FACES: [(IVec3, IVec3, IVec3); 6] = [
+    (IVec3::X, IVec3::Y, IVec3::Z),
+    (IVec3::NEG_X, IVec3::Z, IVec3::Y),
+    (IVec3::Y, IVec3::Z, IVec3::X),
+    (IVec3::NEG_Y, IVec3::X, IVec3::Z),
+    (IVec3::Z, IVec3::X, IVec3::Y),
+    (IVec3::NEG_Z, IVec3::Y, IVec3::X),
+];
+
+/// Closed triangle mesh of the faces between occupied and empty cells.
+/// Vertices are shared between the faces that meet at them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SurfaceMesh {
+    pub positions: Vec<Vec3>,
+    /// Average color of the cells around each vertex.
+    pub colors: Vec<Color>,
+    /// Vertex indices, counter-clockwise seen from outside.
+    pub triangles: Vec<[u32; 3]>,
+}
+
+impl SurfaceMesh {
+    pub fn from_grid<G: Grid + ?Sized>(grid: &G) -> Self {
+        let (min, _) = grid.bounding_box();
+        let occupied = |pos: IVec3| grid.state(pos) != CellState::Empty;
+        let mut fans: HashMap<IVec3, [u8; 12]> = HashMap::new();
+        let mut vertices: HashMap<(IVec3, u8), u32> = HashMap::new();
+        let mut positions = vec![];
+        let mut color_sums: Vec<([f32; 4], f32)> = vec![];
+        let mut triangles = vec![];
+
+        for (pos, cell) in grid.occupied() {
+            let color = grid.color(pos, cell).to_linear();
+            for (normal, u, v) in FACES {
+                if occupied(pos + normal) {
+                    continue;
+                }
+                let base = pos + normal.max(IVec3::ZERO);
+                let corners = [base, base + u, base + u + v, base + v].map(|corner| {
+                    let fan = fans
+                        .entry(corner)
+                        .or_insert_with(|| corner_fans(&occupied, corner))
+                        [block_face(pos - corner, normal)];
+                    let idx = *vertices.entry((corner, fan)).or_insert_with(|| {
+                        positions.push((corner - min).as_vec3());
+                        color_sums.push(([0.0; 4], 0.0));
+                        positions.len() as u32 - 1
+                    });
+                    let (sum, count) = &mut color_sums[idx as usize];
+                    for (sum, channel) in sum.iter_mut().zip(color) {
+                        *sum += channel;
+                    }
+                    *count += 1.0;
+                    idx
+                });
+                triangles.push([corners[0], corners[1], corners[2]]);
+                triangles.push([corners[0], corners[2], corners[3]]);
+            }
+        }
+
+        let colors = color_sums
+            .into_iter()
+            .map(|(sum, count)| Color::from_linear(sum.map(|channel| channel / count)))
+            .collect();
+        Self {
+            positions,
+            colors,
+            triangles,
+        }
+    }
+
+    /// Wavefront OBJ, with the vertex colors after the positions as most tools read them.
+    pub fn write_obj(&self, writer: &mut impl Write) -> io::Result<()> {
+        for (pos, color) in self.positions.iter().zip(&self.colors) {
+            writeln!(
+                writer,
+                "v {} {} {} {} {} {}",
+                pos.x, pos.y, pos.z, color.red, color.green, color.blue
+            )?;
+        }
+        for [a, b, c] in &self.triangles {
+            writeln!(writer, "f {} {} {}", a + 1, b + 1, c + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Binary STL, which has no vertex colors.
+    pub fn write_stl(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(&[0; 80])?;
+        writer.write_all(&(self.triangles.len() as u32).to_le_bytes())?;
+        for triangle in &self.triangles {
+            let [a, b, c] = triangle.map(|idx| self.positions[idx as usize]);
+            let normal = (b - a).cross(c - a).normalize_or_zero();
+            for vector in [normal, a, b, c] {
+                for coord in vector.to_array() {
+                    writer.write_all(&coord.to_le_bytes())?;
+                }
+            }
+            writer.write_all(&0u16.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Binary little endian PLY with 8 bit vertex colors.
+    pub fn write_ply(&self, writer: &mut impl Write) -> io::Result<()> {
+        write!(
+            writer,
+            "ply\n\
+             format binary_little_endian 1.0\n\
+             element vertex {}\n\
+             property float x\n\
+             property float y\n\
+             property float z\n\
+             property uchar red\n\
+             property uchar green\n\
+             property uchar blue\n\
+             element face {}\n\
+             property list uchar uint vertex_indices\n\
+             end_header\n",
+            self.positions.len(),
+            self.triangles.len()
+        )?;
+        for (pos, color) in self.positions.iter().zip(&self.colors) {
+            for coord in pos.to_array() {
+                writer.write_all(&coord.to_le_bytes())?;
+            }
+            let [red, green, blue, _] = color.to_array();
+            writer.write_all(
+                &[red, green, blue].map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8),
+            )?;
+        }
+        for triangle in &self.triangles {
+            writer.write_all(&[3])?;
+            for idx in triangle {
+                writer.write_all(&idx.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, format: MeshFormat, writer: &mut impl Write) -> io::Result<()> {
+        match format {
+            MeshFormat::Obj => self.write_obj(writer),
+            MeshFormat::Stl => self.write_stl(writer),
+            MeshFormat::Ply => self.write_ply(writer),
+        }
+    }
+}
+
+/// Cell of the 2×2×2 block around a corner, from 0 to 7 with a bit per axis
+/// set for the cells past the corner.
+fn block_cell(corner: IVec3, cell: usize) -> IVec3 {
+    let cell = cell as i32;
+    corner + IVec3::new(cell & 1, cell >> 1 & 1, cell >> 2 & 1) - IVec3::ONE
+}
+
+/// Index, from 0 to 11, of the face between the block cells `a` and `b`,
+/// which differ along a single axis.
+fn face_between(a: usize, b: usize) -> usize {
+    let axis = (a ^ b).trailing_zeros() as usize;
+    let lower = a & b;
+    axis * 4 + (lower >> ((axis + 1) % 3) & 1) + 2 * (lower >> ((axis + 2) % 3) & 1)
+}
+
+/// Index of the face of the cell `offset` away from a corner, `offset` being
+/// -1 or 0 along each axis, that points along `normal`.
+fn block_face(offset: IVec3, normal: IVec3) -> usize {
+    let offset = offset + IVec3::ONE;
+    let cell = (offset.x + offset.y * 2 + offset.z * 4) as usize;
+    let axis = normal
+        .abs()
+        .to_array()
+        .iter()
+        .position(|c| *c == 1)
+        .unwrap();
+    face_between(cell, cell ^ 1 << axis)
+}
+
+/// Splits the faces meeting at `corner` in fans, the faces around one vertex
+/// of a manifold surface, and gives each face the smallest index of its fan.
+///
+/// Around each edge leaving the corner the exposed faces are paired two by
+/// two. When only two diagonal cells are occupied, their four faces are
+/// paired by occupied cell, keeping the cells apart, unless the cells are
+/// joined past both ends of the edge: the fans at both ends would then hold
+/// all four faces and the edge would have four triangles, so the faces are
+/// paired by empty cell instead.
+fn corner_fans(occupied: &impl Fn(IVec3) -> bool, corner: IVec3) -> [u8; 12] {
+    let occupied_cells: [bool; 8] = std::array::from_fn(|cell| occupied(block_cell(corner, cell)));
+    let mut fans: [u8; 12] = std::array::from_fn(|face| face as u8);
+    let find = |fans: &[u8; 12], mut face: u8| {
+        while fans[face as usize] != face {
+            face = fans[face as usize];
+        }
+        face
+    };
+    let join = |fans: &mut [u8; 12], a: usize, b: usize| {
+        let (a, b) = (find(fans, a as u8), find(fans, b as u8));
+        fans[a.max(b) as usize] = a.min(b);
+    };
+
+    for axis in 0..3 {
+        let (i, j) = ((axis + 1) % 3, (axis + 2) % 3);
+        for side in 0..2 {
+            // cells around the edge going from the corner towards `side`
+            let ring =
+                [(0, 0), (1, 0), (1, 1), (0, 1)].map(|(a, b)| side << axis | a << i | b << j);
+            // exposed faces with their occupied and empty cell
+            let exposed: Vec<(usize, usize, usize)> = (0..4)
+                .map(|k| (ring[k], ring[(k + 1) % 4]))
+                .filter(|(a, b)| occupied_cells[*a] != occupied_cells[*b])
+                .map(|(a, b)| {
+                    let (full, empty) = if occupied_cells[a] { (a, b) } else { (b, a) };
+                    (face_between(a, b), full, empty)
+                })
+                .collect();
+            match exposed[..] {
+                [(a, ..), (b, ..)] => join(&mut fans, a, b),
+                [first, second, third, fourth] => {
+                    let step = IVec3::AXES[axis] * (2 * side as i32 - 1);
+                    // whether the cells of `layer` join the two occupied cells
+                    let joins = |layer: [bool; 4]| {
+                        let diagonal = if occupied_cells[ring[0]] { 0 } else { 1 };
+                        layer[diagonal]
+                            && layer[diagonal + 2]
+                            && (layer[1 - diagonal] || layer[3 - diagonal])
+                    };
+                    let before = ring.map(|cell| occupied_cells[cell ^ 1 << axis]);
+                    let after = ring.map(|cell| occupied(block_cell(corner, cell) + step));
+                    let by_empty = joins(before) && joins(after);
+                    for (a, b) in [
+                        (first, second),
+                        (second, third),
+                        (third, fourth),
+                        (fourth, first),
+                    ] {
+                        let shared = if by_empty { a.2 == b.2 } else { a.1 == b.1 };
+                        if shared {
+                            join(&mut fans, a.0, b.0);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    std::array::from_fn(|face| find(&fans, face as u8))
+}
+
+/// Rectangle of coplanar faces of the same color, see `greedy_quads`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quad {
+    /// Counter-clockwise seen from outside, relative to the smallest corner
+    /// of the bounding box like `SurfaceMesh`.
+    pub corners: [Vec3; 4],
+    pub normal: IVec3,
+    pub color: Color,
+}
+
+/// The exposed faces of the occupied cells merged into as few rectangles as
+/// the greedy meshing algorithm finds, joining neighbouring faces that face
+/// the same way and have the same 8 bit color. Unlike `SurfaceMesh` the
+/// quads do not share vertices, which is fine for drawing but not printing.
+pub fn greedy_quads<G: Grid + ?Sized>(grid: &G) -> Vec<Quad> {
+    let (min, max) = grid.bounding_box();
+    let size = max - min;
+    let inside = |pos: IVec3| pos.cmpge(IVec3::ZERO).all() && pos.cmplt(size).all();
+    let occupied = |pos: IVec3| inside(pos) && grid.state(pos + min) != CellState::Empty;
+
+    let mut quads = vec![];
+    // color of each exposed face of the current layer, taken once merged,
+    // sized for the largest layer and reused by every layer
+    let largest = size.element_product() / size.min_element().max(1);
+    let mut mask: Vec<Option<[u8; 4]>> = Vec::with_capacity(largest.max(0) as usize);
+    for (normal, u, v) in FACES {
+        let axis = |dir: IVec3| dir.abs().to_array().iter().position(|c| *c == 1).unwrap();
+        let (n_axis, u_axis, v_axis) = (axis(normal), axis(u), axis(v));
+        let (width, height) = (size[u_axis], size[v_axis]);
+
+        for layer in 0..size[n_axis] {
+            let cell_at = |a: i32, b: i32| {
+                let mut pos = IVec3::ZERO;
+                pos[n_axis] = layer;
+                pos[u_axis] = a;
+                pos[v_axis] = b;
+                pos
+            };
+            mask.clear();
+            mask.extend((0..width * height).map(|i| {
+                let pos = cell_at(i % width, i / width);
+                let cell = grid.cell(pos + min);
+                let exposed = cell.state != CellState::Empty && !occupied(pos + normal);
+                exposed.then(|| {
+                    let color = grid.color(pos + min, cell).to_array();
+                    color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8)
+                })
+            }));
+
+            for b in 0..height {
+                let mut a = 0;
+                while a < width {
+                    let Some(color) = mask[(a + b * width) as usize] else {
+                        a += 1;
+                        continue;
+                    };
+                    let same = |a: i32, b: i32| mask[(a + b * width) as usize] == Some(color);
+                    let mut quad_width = 1;
+                    while a + quad_width < width && same(a + quad_width, b) {
+                        quad_width += 1;
+                    }
+                    let mut quad_height = 1;
+                    while b + quad_height < height
+                        && (a..a + quad_width).all(|a| same(a, b + quad_height))
+                    {
+                        quad_height += 1;
+                    }
+                    for b in b..b + quad_height {
+                        for a in a..a + quad_width {
+                            mask[(a + b * width) as usize] = None;
+                        }
+                    }
+
+                    let base = cell_at(a, b) + normal.max(IVec3::ZERO);
+                    let (du, dv) = (u * quad_width, v * quad_height);
+                    quads.push(Quad {
+                        corners: [base, base + du, base + du + dv, base + dv]
+                            .map(|corner| corner.as_vec3()),
+                        normal,
+                        color: Color {
+                            red: color[0] as f32 / 255.0,
+                            green: color[1] as f32 / 255.0,
+                            blue: color[2] as f32 / 255.0,
+                            alpha: color[3] as f32 / 255.0,
+                        },
+                    });
+                    a += quad_width;
+                }
+            }
+        }
+    }
+    quads
+}
+
+/// Ambient occlusion of the four corners of each face of the cell at `pos`,
+/// from 0 for a corner hidden by both neighbouring edges to 3 for a corner
+/// with nothing around it. Faces are in the order +X, -X, +Y, -Y, +Z, -Z and
+/// corners in the order -u-v, +u-v, -u+v, +u+v, with the `u` and `v` edges
+/// of `FACES`.
+pub fn vertex_ao<G: Grid + ?Sized>(grid: &G, pos: IVec3) -> [[u8; 4]; 6] {
+    let mut occupied = [false; 27];
+    for (i, occupied) in occupied.iter_mut().enumerate() {
+        let offset = IVec3::new(i as i32 % 3, i as i32 / 3 % 3, i as i32 / 9) - IVec3::ONE;
+        *occupied = grid.state(pos + offset) != CellState::Empty;
+    }
+    let at = |offset: IVec3| {
+        let offset = offset + IVec3::ONE;
+        occupied[(offset.x + offset.y * 3 + offset.z * 9) as usize]
+    };
+
+    FACES.map(|(normal, u, v)| {
+        [(-u, -v), (u, -v), (-u, v), (u, v)].map(|(u, v)| {
+            let (side_1, side_2) = (at(normal + u), at(normal + v));
+            if side_1 && side_2 {
+                0
+            } else {
+                3 - side_1 as u8 - side_2 as u8 - at(normal + u + v) as u8
+            }
+        })
+    })
+}
+
+/// `vertex_ao` in 48 bits, two bits per corner starting from the lowest,
+/// face after face.
+pub fn pack_ao(ao: [[u8; 4]; 6]) -> u64 {
+    ao.iter()
+        .flatten()
+        .enumerate()
+        .fold(0, |packed, (i, ao)| packed | (*ao as u64) << (i * 2))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MeshFormat {
+    #[default]
+    Obj,
+    Stl,
+    Ply,
+}
+impl MeshFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            MeshFormat::Obj => "obj",
+            MeshFormat::Stl => "stl",
+            MeshFormat::Ply => "ply",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton_grid::AutomatonGrid;
+    use crate::color::ColorMethod;
+    use crate::initial_condition::InitialShape;
+    use crate::vox::{VoxFit, VoxModel};
+    use glam::UVec3;
+
+    fn grid_with(cells: &[UVec3]) -> AutomatonGrid {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(8));
+        let model = VoxModel {
+            size: UVec3::splat(8),
+            voxels: cells.iter().map(|pos| (*pos, 1)).collect(),
+            palette: None,
+        };
+        grid.stamp_vox(&model, VoxFit::Crop);
+        grid
+    }
+
+    /// Every directed edge is matched by the opposite one, so the surface is
+    /// closed and consistently oriented.
+    fn assert_watertight(mesh: &SurfaceMesh) {
+        let mut edges: HashMap<(u32, u32), i32> = HashMap::new();
+        for [a, b, c] in &mesh.triangles {
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                *edges.entry((*from.min(to), *from.max(to))).or_default() +=
+                    if from < to { 1 } else { -1 };
+            }
+        }
+        assert!(edges.values().all(|count| *count == 0));
+    }
+
+    /// Every edge is used by exactly two triangles, which slicers require.
+    fn assert_manifold(mesh: &SurfaceMesh) {
+        let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+        for [a, b, c] in &mesh.triangles {
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                *edges.entry((*from.min(to), *from.max(to))).or_default() += 1;
+            }
+        }
+        assert!(edges.values().all(|count| *count == 2));
+    }
+
+    /// Signed volume enclosed by the mesh, positive when the faces point out.
+    fn volume(mesh: &SurfaceMesh) -> f32 {
+        mesh.triangles
+            .iter()
+            .map(|triangle| {
+                let [a, b, c] = triangle.map(|idx| mesh.positions[idx as usize]);
+                a.dot(b.cross(c)) / 6.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn single_cell_is_a_cube() {
+        let mesh = SurfaceMesh::from_grid(&grid_with(&[UVec3::splat(1)]));
+        assert_eq!(mesh.positions.len(), 8);
+        assert_eq!(mesh.triangles.len(), 12);
+        assert_watertight(&mesh);
+        assert_eq!(volume(&mesh).round(), 1.0);
+    }
+
+    #[test]
+    fn shared_faces_are_skipped() {
+        let mesh = SurfaceMesh::from_grid(&grid_with(&[UVec3::new(1, 1, 1), UVec3::new(2, 1, 1)]));
+        assert_eq!(mesh.positions.len(), 12);
+        assert_eq!(mesh.triangles.len(), 20);
+        assert_watertight(&mesh);
+        assert_eq!(volume(&mesh).round(), 2.0);
+    }
+
+    #[test]
+    fn noise_surface_is_closed() {
+        let mut grid = AutomatonGrid {
+            seed: 6,
+            ..Default::default()
+        };
+        grid.initial_condition.shape = InitialShape::RandomFill;
+        grid.set_size(UVec3::new(12, 10, 8));
+        for _ in 0..3 {
+            grid.update();
+        }
+        let mesh = SurfaceMesh::from_grid(&grid);
+        assert_watertight(&mesh);
+        assert_manifold(&mesh);
+        assert_eq!(volume(&mesh).round() as usize, grid.occupied().count());
+    }
+
+    #[test]
+    fn cells_touching_along_an_edge_keep_apart() {
+        let mesh = SurfaceMesh::from_grid(&grid_with(&[UVec3::new(1, 1, 1), UVec3::new(2, 2, 1)]));
+        assert_eq!(mesh.positions.len(), 16);
+        assert_eq!(mesh.triangles.len(), 24);
+        assert_watertight(&mesh);
+        assert_manifold(&mesh);
+
+        // the same two cells, also joined by cells past both ends of the edge
+        let mut cells = vec![UVec3::new(1, 1, 2), UVec3::new(2, 2, 2)];
+        for z in [1, 3] {
+            cells.extend([
+                UVec3::new(1, 1, z),
+                UVec3::new(2, 2, z),
+                UVec3::new(2, 1, z),
+            ]);
+        }
+        let mesh = SurfaceMesh::from_grid(&grid_with(&cells));
+        assert_watertight(&mesh);
+        assert_manifold(&mesh);
+    }
+
+    #[test]
+    fn random_fills_are_manifold() {
+        for seed in 0..40 {
+            let mut grid = AutomatonGrid {
+                seed,
+                ..Default::default()
+            };
+            grid.initial_condition.shape = InitialShape::RandomFill;
+            grid.initial_condition.density = 0.3 + (seed % 5) as f32 * 0.1;
+            grid.set_size(UVec3::splat(6));
+            let mesh = SurfaceMesh::from_grid(&grid);
+            assert_watertight(&mesh);
+            assert_manifold(&mesh);
+            assert_eq!(volume(&mesh).round() as usize, grid.occupied().count());
+        }
+    }
+
+    #[test]
+    fn greedy_quads_merge_flat_faces() {
+        let cube: Vec<UVec3> = (0..27)
+            .map(|i| UVec3::new(i % 3, i / 3 % 3, i / 9) + UVec3::ONE)
+            .collect();
+        let mut grid = grid_with(&cube);
+        grid.color_method = ColorMethod::StateLerp;
+        let quads = greedy_quads(&grid);
+        assert_eq!(quads.len(), 6);
+        for quad in &quads {
+            let [a, b, _, d] = quad.corners;
+            assert_eq!((b - a).cross(d - a), quad.normal.as_vec3() * 9.0);
+        }
+
+        // neighbour colors differ between the faces and corners of the cube
+        grid.color_method = ColorMethod::Neighbour;
+        assert!(greedy_quads(&grid).len() > 6);
+    }
+
+    #[test]
+    fn greedy_quads_cover_the_surface() {
+        let mut grid = AutomatonGrid {
+            seed: 6,
+            color_method: ColorMethod::StateLerp,
+            ..Default::default()
+        };
+        grid.set_size(UVec3::new(12, 10, 8));
+        for _ in 0..3 {
+            grid.update();
+        }
+        let quads = greedy_quads(&grid);
+        let area: f32 = quads
+            .iter()
+            .map(|quad| {
+                (quad.corners[1] - quad.corners[0]).length()
+                    * (quad.corners[3] - quad.corners[0]).length()
+            })
+            .sum();
+        let faces = SurfaceMesh::from_grid(&grid).triangles.len() / 2;
+        assert_eq!(area as usize, faces);
+        assert!(quads.len() < faces);
+    }
+
+    #[test]
+    fn ao_of_known_configurations() {
+        let center = UVec3::splat(3);
+        assert_eq!(
+            vertex_ao(&grid_with(&[center]), center.as_ivec3()),
+            [[3; 4]; 6]
+        );
+
+        // a 3x3 floor under the cell darkens the lower corners of the side
+        // faces and hides the bottom face
+        let mut cells = vec![center];
+        for x in 2..5 {
+            for z in 2..5 {
+                cells.push(UVec3::new(x, 2, z));
+            }
+        }
+        let ao = vertex_ao(&grid_with(&cells), center.as_ivec3());
+        assert_eq!(ao[0], [1, 3, 1, 3]); // +X, u is Y
+        assert_eq!(ao[1], [1, 1, 3, 3]); // -X, v is Y
+        assert_eq!(ao[2], [3; 4]);
+        assert_eq!(ao[3], [0; 4]);
+        assert_eq!(ao[4], [1, 1, 3, 3]); // +Z, v is Y
+        assert_eq!(ao[5], [1, 3, 1, 3]); // -Z, u is Y
+
+        // an inner corner: both edges of a corner occupied, the diagonal not
+        let ao = vertex_ao(
+            &grid_with(&[center, UVec3::new(4, 4, 3), UVec3::new(3, 4, 4)]),
+            center.as_ivec3(),
+        );
+        assert_eq!(ao[2], [3, 2, 2, 0]); // +Y, u is Z and v is X
+        assert_eq!(ao[0][3], 2); // +X only sees the cell above it
+
+        // a lone diagonal cell only darkens the corner it touches
+        let ao = vertex_ao(
+            &grid_with(&[center, UVec3::new(4, 4, 4)]),
+            center.as_ivec3(),
+        );
+        assert_eq!(ao[2], [3, 3, 3, 2]);
+    }
+
+    #[test]
+    fn packed_ao_keeps_each_corner() {
+        let mut ao = [[3; 4]; 6];
+        ao[0][1] = 1;
+        ao[5][3] = 0;
+        let packed = pack_ao(ao);
+        assert_eq!(packed >> 2 & 3, 1);
+        assert_eq!(packed >> 46 & 3, 0);
+        assert_eq!(packed.count_ones(), 2 * 22 + 1);
+    }
+
+    #[test]
+    fn writers_follow_the_formats() {
+        let mesh = SurfaceMesh::from_grid(&grid_with(&[UVec3::splat(1)]));
+
+        let mut obj = vec![];
+        mesh.write(MeshFormat::Obj, &mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert_eq!(obj.lines().filter(|line| line.starts_with("v ")).count(), 8);
+        assert_eq!(
+            obj.lines().filter(|line| line.starts_with("f ")).count(),
+            12
+        );
+
+        let mut stl = vec![];
+        mesh.write(MeshFormat::Stl, &mut stl).unwrap();
+        assert_eq!(stl.len(), 84 + 12 * 50);
+        assert_eq!(stl[80..84], 12u32.to_le_bytes());
+
+        let mut ply = vec![];
+        mesh.write(MeshFormat::Ply, &mut ply).unwrap();
+        let header_end = b"end_header\n";
+        let body = ply
+            .windows(header_end.len())
+            .position(|window| window == header_end)
+            .unwrap()
+            + header_end.len();
+        assert_eq!(ply.len() - body, 8 * 15 + 12 * 13);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::automaton_grid::{step_cell, AutomatonGrid, Change, Neighbourhood};
+use crate::cell::{CellState, Count, NeighbourCounts};
+use rayon::prelude::*;
+use std::ops::Range;
+
+impl AutomatonGrid {
+    /// Same as the serial `update`, with the grid split in Z layers that are
+    /// stepped on the rayon thread pool.
+    pub fn update_parallel(&mut self) {
+        let layer_len = (self.size.x * self.size.y) as usize;
+        let rule = &self.rule;
+        let cells = &mut self.cells;
+
+        let neighbours = &cells.neighbours;
+        let changes: Vec<(Vec<usize>, Vec<usize>, Vec<usize>)> = cells
+            .states
+            .par_chunks_mut(layer_len)
+            .enumerate()
+            .map(|(z, states)| {
+                let mut spawns = vec![];
+                let mut deaths = vec![];
+                let mut toggles = vec![];
+                for (idx, state) in states.iter_mut().enumerate() {
+                    let was_occupied = *state != CellState::EMPTY;
+                    match step_cell(rule, state, neighbours.get(z * layer_len + idx)) {
+                        Some(Change::Spawn) => spawns.push(z * layer_len + idx),
+                        Some(Change::Death) => deaths.push(z * layer_len + idx),
+                        None => {}
+                    }
+                    if was_occupied != (*state != CellState::EMPTY) {
+                        toggles.push(z * layer_len + idx);
+                    }
+                }
+                (spawns, deaths, toggles)
+            })
+            .collect();
+        // layers are concatenated in order, so the lists stay sorted by index
+        let mut spawns = vec![];
+        let mut deaths = vec![];
+        let mut toggles = vec![];
+        for (layer_spawns, layer_deaths, layer_toggles) in changes {
+            spawns.extend(layer_spawns);
+            deaths.extend(layer_deaths);
+            toggles.extend(layer_toggles);
+        }
+
+        let neighbourhood = Neighbourhood::new(&self.rule, self.size, self.boundary_mode);
+        match &mut self.cells.neighbours {
+            NeighbourCounts::Narrow(counts) => {
+                count_changes(counts, layer_len, &neighbourhood, &spawns, &deaths)
+            }
+            NeighbourCounts::Wide(counts) => {
+                count_changes(counts, layer_len, &neighbourhood, &spawns, &deaths)
+            }
+        }
+
+        // far fewer cells change occupancy than get stepped, so this stays serial
+        for idx in toggles {
+            self.update_faces(idx);
+        }
+    }
+}
+
+/// Counts the `spawns` and `deaths` in the neighbour counts, split in Z
+/// layers of `layer_len` cells that are updated on the rayon thread pool.
+fn count_changes<N: Count>(
+    counts: &mut [N],
+    layer_len: usize,
+    neighbourhood: &Neighbourhood,
+    spawns: &[usize],
+    deaths: &[usize],
+) {
+    let depth = (counts.len() / layer_len) as i32;
+    counts
+        .par_chunks_mut(layer_len)
+        .enumerate()
+        .for_each(|(z, layer)| {
+            let z = z as i32;
+            let offset = z as usize * layer_len;
+            // spawns before deaths, in the same order as the serial update
+            for (changed, increase) in [(spawns, true), (deaths, false)] {
+                for range in seen_layers(z, neighbourhood.reach + 1, depth) {
+                    let start =
+                        changed.partition_point(|idx| (*idx / layer_len) < range.start as usize);
+                    let end =
+                        changed.partition_point(|idx| (*idx / layer_len) < range.end as usize);
+                    for idx in &changed[start..end] {
+                        let pos = neighbourhood.idx_to_pos(*idx);
+                        neighbourhood.for_each_viewer(pos, |viewer| {
+                            if viewer.z == z {
+                                layer[neighbourhood.pos_to_idx(viewer) - offset].update(increase);
+                            }
+                        });
+                    }
+                }
+            }
+        });
+}
+
+/// Z layers whose changes may be counted by the cells of layer `z`: the ones
+/// within `margin` of it, plus the ones close to a face as they can be seen
+/// through the boundary. The ranges are sorted and do not overlap.
+fn seen_layers(z: i32, margin: i32, depth: i32) -> Vec<Range<i32>> {
+    let mut ranges = vec![
+        0..margin.min(depth),
+        (z - margin).max(0)..(z + margin + 1).min(depth),
+        (depth - margin).max(0)..depth,
+    ];
+    ranges.sort_by_key(|range| range.start);
+
+    let mut merged: Vec<Range<i32>> = vec![];
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton_grid::BoundaryMode;
+    use crate::initial_condition::InitialShape;
+    use crate::rule::NeighbourMethod;
+    use glam::UVec3;
+
+    #[test]
+    fn seen_layers_merge() {
+        assert_eq!(seen_layers(10, 2, 32), vec![0..2, 8..13, 30..32]);
+        assert_eq!(seen_layers(1, 2, 32), vec![0..4, 30..32]);
+        assert_eq!(seen_layers(30, 2, 32), vec![0..2, 28..32]);
+        assert_eq!(seen_layers(2, 2, 4), vec![0..4]);
+    }
+
+    #[test]
+    fn serial_and_parallel_updates_match() {
+        for mode in [
+            BoundaryMode::Toroidal,
+            BoundaryMode::Dead,
+            BoundaryMode::Reflective,
+            BoundaryMode::Clamped,
+        ] {
+            for (neighbour_method, radius) in [
+                (NeighbourMethod::Moore, 1),
+                (NeighbourMethod::VonNeumann, 2),
+            ] {
+                let mut serial = AutomatonGrid::default();
+                serial.rule.neighbour_method = neighbour_method.clone();
+                serial.rule.neighbour_radius = radius;
+                serial.initial_condition.shape = InitialShape::RandomFill;
+                serial.initial_condition.density = 0.2;
+                serial.boundary_mode = mode;
+                serial.seed = 11;
+                serial.set_size(UVec3::new(20, 16, 12));
+
+                let mut parallel = AutomatonGrid {
+                    rule: serial.rule.clone(),
+                    initial_condition: serial.initial_condition,
+                    boundary_mode: mode,
+                    seed: 11,
+                    parallel: true,
+                    ..Default::default()
+                };
+                parallel.set_size(serial.size);
+
+                for step in 0..60 {
+                    serial.update();
+                    parallel.update();
+                    assert!(
+                        serial.cells == parallel.cells,
+                        "{mode:?} {neighbour_method:?} diverged at step {step}"
+                    );
+                }
+            }
+        }
+    }
+}
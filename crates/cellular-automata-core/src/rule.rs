@@ -0,0 +1,902 @@
+use crate::cell::CellState;
+use glam::IVec3;
+use std::fmt::Display;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Largest neighbourhood radius, a Moore neighbourhood of this radius has 728 cells.
+pub const MAX_NEIGHBOUR_RADIUS: u8 = 4;
+
+/// A rule written as `survival/birth/states/neighbourhood`, eg. `4/4/5/M`.
+///
+/// The neighbourhood letters are `M` (Moore), `N` (Von Neumann), `E` (faces
+/// and edges), `C` (corners) and `F` (face-centred cubic). `M` and `N` take an
+/// optional radius like `M2`, custom neighbourhoods list their offsets as
+/// `X(1,0,0)(-1,0,0)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub survival_rule: Indexes,
+    pub birth_rule: Indexes,
+    pub states: u8,
+    pub neighbour_method: NeighbourMethod,
+    /// Distance up to which cells count as neighbours, the classic rules use 1.
+    pub neighbour_radius: u8,
+}
+impl Rule {
+    pub fn get_neighbour_iter(&self) -> &[IVec3] {
+        self.neighbour_method.get_iter(self.neighbour_radius)
+    }
+
+    /// Largest distance along any axis at which a neighbour is counted.
+    pub fn get_neighbour_reach(&self) -> i32 {
+        match &self.neighbour_method {
+            NeighbourMethod::Moore | NeighbourMethod::VonNeumann => {
+                self.neighbour_radius.clamp(1, MAX_NEIGHBOUR_RADIUS) as i32
+            }
+            _ => self
+                .get_neighbour_iter()
+                .iter()
+                .map(|offset| offset.abs().max_element())
+                .max()
+                .unwrap_or(0),
+        }
+    }
+
+    pub fn get_max_neighbours(&self) -> u16 {
+        self.get_neighbour_iter().len() as u16
+    }
+
+    pub fn apply_birth_rule(&self, neighbors: u16) -> CellState {
+        if self.birth_rule.has(neighbors) {
+            CellState::Alive
+        } else {
+            CellState::Empty
+        }
+    }
+
+    pub fn apply_survival_rule(&self, neighbors: u16) -> CellState {
+        if !self.survival_rule.has(neighbors) {
+            // eg. if cells has 5 states
+            // empty -> state 0
+            // dying -> state 1,2,3
+            // alive -> state 4
+            CellState::Dying(self.states - 2)
+        } else {
+            CellState::Alive
+        }
+    }
+
+    pub fn apply_dying_rule(&self, state: u8) -> CellState {
+        if state == 1 {
+            CellState::Empty
+        } else {
+            CellState::Dying(state - 1)
+        }
+    }
+}
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}/",
+            self.survival_rule.to_string(),
+            self.birth_rule.to_string(),
+            self.states
+        )?;
+        match &self.neighbour_method {
+            NeighbourMethod::Moore => write!(f, "M")?,
+            NeighbourMethod::VonNeumann => write!(f, "N")?,
+            NeighbourMethod::FacesAndEdges => return write!(f, "E"),
+            NeighbourMethod::Corners => return write!(f, "C"),
+            NeighbourMethod::FaceCenteredCubic => return write!(f, "F"),
+            NeighbourMethod::Custom(offsets) => {
+                write!(f, "X")?;
+                for offset in offsets {
+                    write!(f, "({},{},{})", offset.x, offset.y, offset.z)?;
+                }
+                return Ok(());
+            }
+        }
+        if self.neighbour_radius > 1 {
+            write!(f, "{}", self.neighbour_radius)?;
+        }
+        Ok(())
+    }
+}
+impl FromStr for Rule {
+    type Err = RuleNotationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let fields: Vec<&str> = s.trim().split('/').map(str::trim).collect();
+        let [survival, birth, states, neighbourhood] = fields[..] else {
+            return Err(RuleNotationError::FieldCount(fields.len()));
+        };
+
+        let (neighbour_method, neighbour_radius) = parse_neighbourhood(neighbourhood)
+            .ok_or_else(|| RuleNotationError::Neighbourhood(neighbourhood.to_string()))?;
+        let max = neighbour_method.get_iter(neighbour_radius).len() as u16;
+
+        let survival_rule =
+            Indexes::parse_with_max(survival, max).map_err(RuleNotationError::Survival)?;
+        let birth_rule = Indexes::parse_with_max(birth, max).map_err(RuleNotationError::Birth)?;
+        let states = match states.parse() {
+            Ok(states) if states >= 2 => states,
+            _ => return Err(RuleNotationError::States(states.to_string())),
+        };
+
+        Ok(Self {
+            survival_rule,
+            birth_rule,
+            states,
+            neighbour_method,
+            neighbour_radius,
+        })
+    }
+}
+
+fn parse_neighbourhood(s: &str) -> Option<(NeighbourMethod, u8)> {
+    let upper = s.to_ascii_uppercase();
+    if let Some(offsets) = upper.strip_prefix('X') {
+        let offsets = offsets.trim();
+        if offsets.is_empty() {
+            return Some((NeighbourMethod::Custom(vec![]), 1));
+        }
+        let offsets = offsets.strip_prefix('(')?.strip_suffix(')')?;
+        let mut result = vec![];
+        for offset in offsets.split(")(") {
+            let coords: Vec<i32> = offset
+                .split(',')
+                .map(|coord| coord.trim().parse().ok())
+                .collect::<Option<_>>()?;
+            let [x, y, z] = coords[..] else {
+                return None;
+            };
+            result.push(IVec3::new(x, y, z));
+        }
+        return Some((NeighbourMethod::custom(result), 1));
+    }
+
+    let letters = upper.trim_end_matches(|c: char| c.is_ascii_digit());
+    let radius = match &upper[letters.len()..] {
+        "" => 1,
+        radius => radius.parse().ok()?,
+    };
+    let method = match letters {
+        "M" => NeighbourMethod::Moore,
+        "N" | "VN" | "NN" => NeighbourMethod::VonNeumann,
+        "E" => NeighbourMethod::FacesAndEdges,
+        "C" => NeighbourMethod::Corners,
+        "F" => NeighbourMethod::FaceCenteredCubic,
+        _ => return None,
+    };
+    let has_radius = matches!(method, NeighbourMethod::Moore | NeighbourMethod::VonNeumann);
+    if !(1..=MAX_NEIGHBOUR_RADIUS).contains(&radius) || (!has_radius && radius != 1) {
+        return None;
+    }
+    Some((method, radius))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleNotationError {
+    /// The notation needs exactly four fields separated by `/`.
+    FieldCount(usize),
+    Survival(RuleParseError),
+    Birth(RuleParseError),
+    States(String),
+    Neighbourhood(String),
+}
+impl Display for RuleNotationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RuleNotationError::FieldCount(count) => write!(
+                f,
+                "expected 4 fields as survival/birth/states/neighbourhood, found {count}"
+            ),
+            RuleNotationError::Survival(err) => write!(f, "invalid survival counts, {err}"),
+            RuleNotationError::Birth(err) => write!(f, "invalid birth counts, {err}"),
+            RuleNotationError::States(field) => {
+                write!(
+                    f,
+                    "invalid states \"{field}\", expected a number from 2 to 255"
+                )
+            }
+            RuleNotationError::Neighbourhood(field) => write!(
+                f,
+                "invalid neighbourhood \"{field}\", expected M, N, E, C, F or X followed by offsets"
+            ),
+        }
+    }
+}
+impl std::error::Error for RuleNotationError {}
+
+/// Why a list of neighbour counts such as `2,6,9-11` could not be parsed.
+///
+/// Every variant carries the offending comma separated token and its byte
+/// offset in the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RuleParseError {
+    NotANumber {
+        token: String,
+        position: usize,
+    },
+    OutOfRange {
+        token: String,
+        position: usize,
+        max: u16,
+    },
+    ReversedRange {
+        token: String,
+        position: usize,
+    },
+}
+impl RuleParseError {
+    pub fn token(&self) -> &str {
+        match self {
+            RuleParseError::NotANumber { token, .. }
+            | RuleParseError::OutOfRange { token, .. }
+            | RuleParseError::ReversedRange { token, .. } => token,
+        }
+    }
+
+    pub fn position(&self) -> usize {
+        match self {
+            RuleParseError::NotANumber { position, .. }
+            | RuleParseError::OutOfRange { position, .. }
+            | RuleParseError::ReversedRange { position, .. } => *position,
+        }
+    }
+}
+impl Display for RuleParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (token, column) = (self.token(), self.position() + 1);
+        match self {
+            RuleParseError::NotANumber { .. } => {
+                write!(f, "\"{token}\" at column {column} is not a number or range")
+            }
+            RuleParseError::OutOfRange { max, .. } => write!(
+                f,
+                "\"{token}\" at column {column} is out of range, the maximum is {max}"
+            ),
+            RuleParseError::ReversedRange { .. } => write!(
+                f,
+                "\"{token}\" at column {column} is a reversed range, write the lower bound first"
+            ),
+        }
+    }
+}
+impl std::error::Error for RuleParseError {}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            survival_rule: Indexes::new(&[2, 6, 9]),
+            birth_rule: Indexes::new(&[4, 6, 8, 9, 11]),
+            states: 10,
+            neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
+        }
+    }
+}
+
+/// Set of neighbour counts, grows past the 26 of a Moore neighbourhood
+/// as needed by larger neighbourhoods.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Indexes(Vec<bool>);
+impl Indexes {
+    /// Largest count any neighbourhood can reach.
+    pub const MAX: u16 = (2 * MAX_NEIGHBOUR_RADIUS as u16 + 1).pow(3) - 1;
+
+    pub fn new(indexes: &[u16]) -> Self {
+        let mut result = Self::default();
+        for index in indexes {
+            result.insert(*index);
+        }
+        result
+    }
+    pub fn from_range(indexes: RangeInclusive<u16>) -> Self {
+        let mut result = Self::default();
+        for index in indexes {
+            result.insert(index);
+        }
+        result
+    }
+    pub fn has(&self, idx: u16) -> bool {
+        self.0.get(idx as usize).copied().unwrap_or(false)
+    }
+    fn insert(&mut self, idx: u16) {
+        let idx = idx as usize;
+        if idx >= self.0.len() {
+            self.0.resize(idx + 1, false);
+        }
+        self.0[idx] = true;
+    }
+    pub fn parse_str(s: &str) -> Result<Self, RuleParseError> {
+        Self::parse_with_max(s, Self::MAX)
+    }
+    /// Same as `parse_str`, rejecting counts above `max`.
+    pub fn parse_with_max(s: &str, max: u16) -> Result<Self, RuleParseError> {
+        let mut res = Indexes::default();
+        if s.trim().is_empty() {
+            return Ok(res);
+        }
+        let mut offset = 0;
+        for value in s.split(",") {
+            let position = offset + value.len() - value.trim_start().len();
+            offset += value.len() + 1;
+
+            let token = value.trim();
+            let not_a_number = || RuleParseError::NotANumber {
+                token: token.to_string(),
+                position,
+            };
+            let (start, end) = match token.split_once('-') {
+                Some((start, end)) => (start.trim(), end.trim()),
+                None => (token, token),
+            };
+            let start: u16 = start.parse().map_err(|_| not_a_number())?;
+            let end: u16 = end.parse().map_err(|_| not_a_number())?;
+            if start > end {
+                return Err(RuleParseError::ReversedRange {
+                    token: token.to_string(),
+                    position,
+                });
+            }
+            if end > max {
+                return Err(RuleParseError::OutOfRange {
+                    token: token.to_string(),
+                    position,
+                    max,
+                });
+            }
+            for idx in start..=end {
+                res.insert(idx);
+            }
+        }
+        Ok(res)
+    }
+}
+impl Default for Indexes {
+    fn default() -> Self {
+        Self(vec![false; MOORE_NEIGHBOURS.len() + 1])
+    }
+}
+
+#[allow(clippy::to_string_trait_impl)]
+impl ToString for Indexes {
+    fn to_string(&self) -> String {
+        let mut result = String::new();
+        let last = self.0.len() - 1;
+        let mut start = 0;
+        while start <= last {
+            if self.0[start] {
+                let mut end = start;
+                while end < last && self.0[end + 1] {
+                    end += 1;
+                }
+                if start == end {
+                    result.push_str(&format!("{},", start));
+                } else if start + 1 == end {
+                    result.push_str(&format!("{},{},", start, end));
+                } else {
+                    result.push_str(&format!("{}-{},", start, end));
+                }
+                start += (end - start) + 1;
+            } else {
+                start += 1;
+            }
+        }
+        result.trim_end_matches(',').to_string()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NeighbourMethod {
+    Moore,
+    VonNeumann,
+    /// Cells sharing a face or an edge, the Moore neighbourhood without corners.
+    FacesAndEdges,
+    /// Cells sharing only a corner.
+    Corners,
+    /// Cells sharing an edge, the 12 neighbours of a face-centred cubic lattice.
+    FaceCenteredCubic,
+    /// Any set of offsets, build it with `NeighbourMethod::custom`.
+    Custom(Vec<IVec3>),
+}
+impl NeighbourMethod {
+    /// Custom neighbourhood without duplicates, the cell itself or offsets
+    /// further than `MAX_NEIGHBOUR_RADIUS`.
+    pub fn custom(offsets: impl IntoIterator<Item = IVec3>) -> Self {
+        let mut result = vec![];
+        for offset in offsets {
+            let in_range = offset.abs().max_element() <= MAX_NEIGHBOUR_RADIUS as i32;
+            if in_range && offset != IVec3::ZERO && !result.contains(&offset) {
+                result.push(offset);
+            }
+        }
+        NeighbourMethod::Custom(result)
+    }
+
+    /// Whether `get_iter` depends on the radius, only Moore and Von Neumann
+    /// neighbourhoods grow with it.
+    pub fn uses_radius(&self) -> bool {
+        matches!(self, NeighbourMethod::Moore | NeighbourMethod::VonNeumann)
+    }
+
+    pub fn get_iter(&self, radius: u8) -> &[IVec3] {
+        static TABLES: [[OnceLock<Vec<IVec3>>; MAX_NEIGHBOUR_RADIUS as usize]; 2] =
+            [const { [const { OnceLock::new() }; MAX_NEIGHBOUR_RADIUS as usize] }; 2];
+
+        let table = match (self, radius) {
+            (NeighbourMethod::Moore, 0 | 1) => return &MOORE_NEIGHBOURS[..],
+            (NeighbourMethod::VonNeumann, 0 | 1) => return &VONNEUMAN_NEIGHBOURS[..],
+            (NeighbourMethod::FacesAndEdges, _) => return &FACE_EDGE_NEIGHBOURS[..],
+            (NeighbourMethod::Corners, _) => return &CORNER_NEIGHBOURS[..],
+            (NeighbourMethod::FaceCenteredCubic, _) => return &FCC_NEIGHBOURS[..],
+            (NeighbourMethod::Custom(offsets), _) => return offsets,
+            (NeighbourMethod::Moore, _) => &TABLES[0],
+            (NeighbourMethod::VonNeumann, _) => &TABLES[1],
+        };
+        let radius = radius.min(MAX_NEIGHBOUR_RADIUS);
+        table[radius as usize - 1].get_or_init(|| self.generate(radius))
+    }
+
+    /// Builds the offsets of the neighbourhood with the given radius,
+    /// in the same order as `MOORE_NEIGHBOURS`. The face, edge and corner
+    /// based neighbourhoods only exist with radius 1.
+    pub fn generate(&self, radius: u8) -> Vec<IVec3> {
+        let radius = radius as i32;
+        let mut offsets = vec![];
+        for z in -radius..=radius {
+            for y in -radius..=radius {
+                for x in -radius..=radius {
+                    let offset = IVec3::new(x, y, z);
+                    let adjacent = offset.abs().max_element() <= 1;
+                    let shared_axes = offset.abs().element_sum();
+                    let inside = match self {
+                        NeighbourMethod::Moore => true,
+                        NeighbourMethod::VonNeumann => offset.abs().element_sum() <= radius,
+                        NeighbourMethod::FacesAndEdges => adjacent && shared_axes <= 2,
+                        NeighbourMethod::Corners => adjacent && shared_axes == 3,
+                        NeighbourMethod::FaceCenteredCubic => adjacent && shared_axes == 2,
+                        NeighbourMethod::Custom(offsets) => offsets.contains(&offset),
+                    };
+                    if inside && offset != IVec3::ZERO {
+                        offsets.push(offset);
+                    }
+                }
+            }
+        }
+        offsets
+    }
+}
+
+pub static VONNEUMAN_NEIGHBOURS: [IVec3; 6] = [
+    IVec3::new(1, 0, 0),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(0, -1, 0),
+    IVec3::new(0, 0, -1),
+    IVec3::new(0, 0, 1),
+];
+
+pub static FACE_EDGE_NEIGHBOURS: [IVec3; 18] = [
+    IVec3::new(0, -1, -1),
+    IVec3::new(-1, 0, -1),
+    IVec3::new(0, 0, -1),
+    IVec3::new(1, 0, -1),
+    IVec3::new(0, 1, -1),
+    IVec3::new(-1, -1, 0),
+    IVec3::new(0, -1, 0),
+    IVec3::new(1, -1, 0),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(1, 0, 0),
+    IVec3::new(-1, 1, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(1, 1, 0),
+    IVec3::new(0, -1, 1),
+    IVec3::new(-1, 0, 1),
+    IVec3::new(0, 0, 1),
+    IVec3::new(1, 0, 1),
+    IVec3::new(0, 1, 1),
+];
+
+pub static CORNER_NEIGHBOURS: [IVec3; 8] = [
+    IVec3::new(-1, -1, -1),
+    IVec3::new(1, -1, -1),
+    IVec3::new(-1, 1, -1),
+    IVec3::new(1, 1, -1),
+    IVec3::new(-1, -1, 1),
+    IVec3::new(1, -1, 1),
+    IVec3::new(-1, 1, 1),
+    IVec3::new(1, 1, 1),
+];
+
+pub static FCC_NEIGHBOURS: [IVec3; 12] = [
+    IVec3::new(0, -1, -1),
+    IVec3::new(-1, 0, -1),
+    IVec3::new(1, 0, -1),
+    IVec3::new(0, 1, -1),
+    IVec3::new(-1, -1, 0),
+    IVec3::new(1, -1, 0),
+    IVec3::new(-1, 1, 0),
+    IVec3::new(1, 1, 0),
+    IVec3::new(0, -1, 1),
+    IVec3::new(-1, 0, 1),
+    IVec3::new(1, 0, 1),
+    IVec3::new(0, 1, 1),
+];
+
+pub static MOORE_NEIGHBOURS: [IVec3; 26] = [
+    IVec3::new(-1, -1, -1),
+    IVec3::new(0, -1, -1),
+    IVec3::new(1, -1, -1),
+    IVec3::new(-1, 0, -1),
+    IVec3::new(0, 0, -1),
+    IVec3::new(1, 0, -1),
+    IVec3::new(-1, 1, -1),
+    IVec3::new(0, 1, -1),
+    IVec3::new(1, 1, -1),
+    IVec3::new(-1, -1, 0),
+    IVec3::new(0, -1, 0),
+    IVec3::new(1, -1, 0),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(1, 0, 0),
+    IVec3::new(-1, 1, 0),
+    IVec3::new(0, 1, 0),
+    IVec3::new(1, 1, 0),
+    IVec3::new(-1, -1, 1),
+    IVec3::new(0, -1, 1),
+    IVec3::new(1, -1, 1),
+    IVec3::new(-1, 0, 1),
+    IVec3::new(0, 0, 1),
+    IVec3::new(1, 0, 1),
+    IVec3::new(-1, 1, 1),
+    IVec3::new(0, 1, 1),
+    IVec3::new(1, 1, 1),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_indexes_from_slice() {
+        let indexes = Indexes::new(&[1, 2, 3, 26]);
+        let mut expected = [false; 27];
+        expected[1] = true;
+        expected[2] = true;
+        expected[3] = true;
+        expected[4] = false;
+        expected[26] = true;
+
+        assert_eq!(indexes.0, expected);
+    }
+
+    #[test]
+    fn new_indexes_from_range() {
+        let indexes = Indexes::from_range(1..=3);
+        let mut expected = [false; 27];
+        expected[1] = true;
+        expected[2] = true;
+        expected[3] = true;
+
+        assert_eq!(indexes.0, expected);
+    }
+
+    #[test]
+    fn birth_rule() {
+        let rule = Rule::default();
+
+        assert_eq!(rule.apply_birth_rule(0), CellState::Empty);
+        assert_eq!(rule.apply_birth_rule(4), CellState::Alive);
+        assert_eq!(rule.apply_birth_rule(5), CellState::Empty);
+        assert_eq!(rule.apply_birth_rule(9), CellState::Alive);
+    }
+
+    #[test]
+    fn survival_rule() {
+        let rule = Rule::default();
+
+        assert_eq!(
+            rule.apply_survival_rule(0),
+            CellState::Dying(rule.states - 2)
+        );
+        assert_eq!(rule.apply_survival_rule(2), CellState::Alive);
+    }
+
+    #[test]
+    fn dying_rule() {
+        let rule = Rule::default();
+
+        assert_eq!(rule.apply_dying_rule(1), CellState::Empty);
+        assert_eq!(rule.apply_dying_rule(3), CellState::Dying(2));
+    }
+
+    #[test]
+    fn indexes_to_string() {
+        let indexes = Indexes::new(&[1, 2, 4, 5, 6, 7, 15]);
+        assert_eq!(indexes.to_string(), "1,2,4-7,15".to_string());
+
+        let indexes = Indexes::from_range(9..=26);
+        assert_eq!(indexes.to_string(), "9-26".to_string());
+    }
+
+    #[test]
+    fn indexes_from_str() {
+        let inputs = "1,2,4-7,15";
+        let indexes = Indexes::new(&[1, 2, 4, 5, 6, 7, 15]);
+        assert_eq!(Indexes::parse_str(inputs), Ok(indexes));
+
+        let inputs = "9-26";
+        let indexes = Indexes::from_range(9..=26);
+        assert_eq!(Indexes::parse_str(inputs), Ok(indexes));
+    }
+
+    #[test]
+    fn indexes_above_moore_range() {
+        let indexes = Indexes::parse_str("4,30-32,124").unwrap();
+        assert!(indexes.has(31));
+        assert!(indexes.has(124));
+        assert!(!indexes.has(125));
+        assert!(!indexes.has(600));
+        assert_eq!(indexes, Indexes::new(&[4, 30, 31, 32, 124]));
+        assert_eq!(indexes.to_string(), "4,30-32,124".to_string());
+
+        assert!(Indexes::parse_str("900").is_err());
+    }
+
+    #[test]
+    fn indexes_parse_not_a_number() {
+        assert_eq!(
+            Indexes::parse_str("4-"),
+            Err(RuleParseError::NotANumber {
+                token: "4-".to_string(),
+                position: 0
+            })
+        );
+        assert_eq!(
+            Indexes::parse_str("1, 2,x"),
+            Err(RuleParseError::NotANumber {
+                token: "x".to_string(),
+                position: 5
+            })
+        );
+        assert_eq!(
+            Indexes::parse_str("1,,2"),
+            Err(RuleParseError::NotANumber {
+                token: "".to_string(),
+                position: 2
+            })
+        );
+        assert_eq!(
+            Indexes::parse_str("-3"),
+            Err(RuleParseError::NotANumber {
+                token: "-3".to_string(),
+                position: 0
+            })
+        );
+    }
+
+    #[test]
+    fn indexes_parse_out_of_range() {
+        assert_eq!(
+            Indexes::parse_with_max("4,  30", 26),
+            Err(RuleParseError::OutOfRange {
+                token: "30".to_string(),
+                position: 4,
+                max: 26
+            })
+        );
+        assert_eq!(
+            Indexes::parse_with_max("20-27", 26),
+            Err(RuleParseError::OutOfRange {
+                token: "20-27".to_string(),
+                position: 0,
+                max: 26
+            })
+        );
+        assert_eq!(
+            Indexes::parse_str("900"),
+            Err(RuleParseError::OutOfRange {
+                token: "900".to_string(),
+                position: 0,
+                max: Indexes::MAX
+            })
+        );
+        assert!(Indexes::parse_with_max("26", 26).is_ok());
+    }
+
+    #[test]
+    fn indexes_parse_reversed_range() {
+        let err = Indexes::parse_str("1,10-4").unwrap_err();
+        assert_eq!(
+            err,
+            RuleParseError::ReversedRange {
+                token: "10-4".to_string(),
+                position: 2
+            }
+        );
+        assert_eq!(err.token(), "10-4");
+        assert_eq!(err.position(), 2);
+    }
+
+    #[test]
+    fn generated_neighbourhoods() {
+        let moore = NeighbourMethod::Moore.generate(1);
+        assert_eq!(moore, MOORE_NEIGHBOURS.to_vec());
+        let von_neumann = NeighbourMethod::VonNeumann.generate(1);
+        assert_eq!(von_neumann.len(), 6);
+        for offset in VONNEUMAN_NEIGHBOURS {
+            assert!(von_neumann.contains(&offset));
+        }
+
+        assert_eq!(NeighbourMethod::Moore.get_iter(2).len(), 124);
+        assert_eq!(NeighbourMethod::VonNeumann.get_iter(2).len(), 24);
+        assert_eq!(NeighbourMethod::Moore.get_iter(3).len(), 342);
+    }
+
+    #[test]
+    fn max_neighbours_follow_radius() {
+        let mut rule = Rule::default();
+        assert_eq!(rule.get_max_neighbours(), 26);
+
+        rule.neighbour_radius = 2;
+        assert_eq!(rule.get_max_neighbours(), 124);
+        assert_eq!(rule.get_neighbour_reach(), 2);
+        assert_eq!(Indexes::MAX, 728);
+    }
+
+    #[test]
+    fn builtin_tables_are_symmetric_without_duplicates() {
+        let tables: [(NeighbourMethod, &[IVec3], usize); 5] = [
+            (NeighbourMethod::Moore, &MOORE_NEIGHBOURS, 26),
+            (NeighbourMethod::VonNeumann, &VONNEUMAN_NEIGHBOURS, 6),
+            (NeighbourMethod::FacesAndEdges, &FACE_EDGE_NEIGHBOURS, 18),
+            (NeighbourMethod::Corners, &CORNER_NEIGHBOURS, 8),
+            (NeighbourMethod::FaceCenteredCubic, &FCC_NEIGHBOURS, 12),
+        ];
+        for (method, table, len) in tables {
+            assert_eq!(table.len(), len, "{method:?}");
+            for (idx, offset) in table.iter().enumerate() {
+                assert_ne!(*offset, IVec3::ZERO, "{method:?}");
+                assert!(table.contains(&-*offset), "{method:?} {offset}");
+                assert!(!table[idx + 1..].contains(offset), "{method:?} {offset}");
+            }
+
+            let generated = method.generate(1);
+            assert_eq!(generated.len(), len, "{method:?}");
+            assert!(generated.iter().all(|offset| table.contains(offset)));
+
+            let rule = Rule {
+                neighbour_method: method,
+                ..Rule::default()
+            };
+            assert_eq!(rule.get_max_neighbours() as usize, len);
+            assert_eq!(rule.get_neighbour_reach(), 1);
+        }
+    }
+
+    #[test]
+    fn rule_from_notation() {
+        let rule: Rule = "4/4/5/M".parse().unwrap();
+        assert_eq!(
+            rule,
+            Rule {
+                survival_rule: Indexes::new(&[4]),
+                birth_rule: Indexes::new(&[4]),
+                states: 5,
+                neighbour_method: NeighbourMethod::Moore,
+                neighbour_radius: 1,
+            }
+        );
+
+        let rule: Rule = " 2,6,9 / 4,6,8-10 / 10 / M ".parse().unwrap();
+        assert_eq!(rule.survival_rule, Indexes::new(&[2, 6, 9]));
+        assert_eq!(rule.birth_rule, Indexes::new(&[4, 6, 8, 9, 10]));
+        assert_eq!(rule.states, 10);
+
+        let rule: Rule = "5,6/1-3/15/vn".parse().unwrap();
+        assert_eq!(rule.neighbour_method, NeighbourMethod::VonNeumann);
+
+        let rule: Rule = "/30-40/7/M2".parse().unwrap();
+        assert_eq!(rule.survival_rule, Indexes::default());
+        assert_eq!(rule.neighbour_radius, 2);
+
+        let rule: Rule = "1/1/2/X(1,0,0)(-1,0,0)".parse().unwrap();
+        assert_eq!(
+            rule.neighbour_method,
+            NeighbourMethod::Custom(vec![IVec3::new(1, 0, 0), IVec3::new(-1, 0, 0)])
+        );
+    }
+
+    #[test]
+    fn rule_to_notation() {
+        for notation in [
+            "4/4/5/M",
+            "2,6,9/4,6,8-10/10/M",
+            "5,6/1-3/15/N",
+            "9-26/5-7,12,13,15/20/M",
+            "4/30-40/7/M2",
+            "3/2/4/F",
+            "1/1/2/X(1,0,0)(0,-1,2)",
+            "//2/X",
+        ] {
+            let rule: Rule = notation.parse().unwrap();
+            assert_eq!(rule.to_string(), notation);
+        }
+    }
+
+    #[test]
+    fn rule_notation_errors() {
+        assert_eq!(
+            "4/4/5".parse::<Rule>(),
+            Err(RuleNotationError::FieldCount(3))
+        );
+        assert_eq!(
+            "4-/4/5/M".parse::<Rule>(),
+            Err(RuleNotationError::Survival(RuleParseError::NotANumber {
+                token: "4-".to_string(),
+                position: 0
+            }))
+        );
+        assert_eq!(
+            "4/x/5/M".parse::<Rule>(),
+            Err(RuleNotationError::Birth(RuleParseError::NotANumber {
+                token: "x".to_string(),
+                position: 0
+            }))
+        );
+        assert_eq!(
+            "4/27/5/M".parse::<Rule>(),
+            Err(RuleNotationError::Birth(RuleParseError::OutOfRange {
+                token: "27".to_string(),
+                position: 0,
+                max: 26
+            }))
+        );
+        assert_eq!(
+            "4/4/1/M".parse::<Rule>(),
+            Err(RuleNotationError::States("1".to_string()))
+        );
+        assert_eq!(
+            "4/4/5/Q".parse::<Rule>(),
+            Err(RuleNotationError::Neighbourhood("Q".to_string()))
+        );
+        assert_eq!(
+            "4/4/5/F2".parse::<Rule>(),
+            Err(RuleNotationError::Neighbourhood("F2".to_string()))
+        );
+        assert_eq!(
+            "4/4/5/X(1,0)".parse::<Rule>(),
+            Err(RuleNotationError::Neighbourhood("X(1,0)".to_string()))
+        );
+    }
+
+    #[test]
+    fn custom_neighbourhood() {
+        let method = NeighbourMethod::custom([
+            IVec3::new(1, 0, 0),
+            IVec3::new(-1, 0, 0),
+            IVec3::new(1, 0, 0),
+            IVec3::ZERO,
+            IVec3::new(0, 2, 0),
+            IVec3::new(0, 0, 9),
+        ]);
+        assert_eq!(
+            method,
+            NeighbourMethod::Custom(vec![
+                IVec3::new(1, 0, 0),
+                IVec3::new(-1, 0, 0),
+                IVec3::new(0, 2, 0),
+            ])
+        );
+
+        let rule = Rule {
+            neighbour_method: method,
+            ..Rule::default()
+        };
+        assert_eq!(rule.get_max_neighbours(), 3);
+        assert_eq!(rule.get_neighbour_reach(), 2);
+    }
+}
@@ -0,0 +1,93 @@
+use crate::cell::Cell;
+use crate::color::ColorMethod;
+use crate::grid::Grid;
+use glam::IVec3;
+
+/// How big the cube of each cell is drawn, the geometric counterpart of
+/// `ColorMethod`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ScaleMethod {
+    /// Every cell at the largest size.
+    #[default]
+    Constant,
+    /// Alive cells at the largest size, dying cells shrinking as they fade.
+    DyingState,
+    NeighbourCount,
+    DistToCenter,
+}
+impl ScaleMethod {
+    /// Size of `cell`, found at `pos` in `grid`, from `min` to `max`.
+    pub fn get_scale<G: Grid + ?Sized>(
+        &self,
+        grid: &G,
+        pos: IVec3,
+        cell: Cell,
+        min: f32,
+        max: f32,
+    ) -> f32 {
+        min + (max - min) * self.get_factor(grid, pos, cell)
+    }
+
+    /// How far the size of `cell` is from `min` towards `max`, from 0 to 1.
+    pub fn get_factor<G: Grid + ?Sized>(&self, grid: &G, pos: IVec3, cell: Cell) -> f32 {
+        // the same measures the color methods blend by
+        let method = match self {
+            ScaleMethod::Constant => return 1.0,
+            ScaleMethod::DyingState => ColorMethod::StateLerp,
+            ScaleMethod::NeighbourCount => ColorMethod::Neighbour,
+            ScaleMethod::DistToCenter => ColorMethod::DistToCenter,
+        };
+        method.get_blend(grid, pos, cell)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton_grid::AutomatonGrid;
+    use crate::cell::CellState;
+    use glam::UVec3;
+
+    #[test]
+    fn dying_cells_shrink() {
+        let mut grid = AutomatonGrid {
+            rule: "4/4/5/M".parse().unwrap(),
+            ..Default::default()
+        };
+        grid.set_size(UVec3::splat(8));
+        let pos = IVec3::splat(4);
+        let scale = |state| {
+            let cell = Cell {
+                state,
+                neighbours: 0,
+            };
+            ScaleMethod::DyingState.get_scale(&grid, pos, cell, 0.2, 1.0)
+        };
+
+        assert_eq!(scale(CellState::Alive), 1.0);
+        assert!(scale(CellState::Dying(3)) < 1.0);
+        assert!(scale(CellState::Dying(1)) < scale(CellState::Dying(3)));
+        assert!(scale(CellState::Dying(1)) > 0.2);
+        assert_eq!(
+            ScaleMethod::Constant.get_scale(&grid, pos, Cell::default(), 0.2, 0.7),
+            0.7
+        );
+    }
+
+    #[test]
+    fn neighbour_count_scales_up_to_max() {
+        let grid = AutomatonGrid::default();
+        let max_neighbours = grid.rule.get_max_neighbours();
+        let cell = |neighbours| Cell {
+            state: CellState::Alive,
+            neighbours,
+        };
+        let method = ScaleMethod::NeighbourCount;
+
+        assert_eq!(method.get_scale(&grid, IVec3::ZERO, cell(0), 0.5, 1.0), 0.5);
+        assert_eq!(
+            method.get_scale(&grid, IVec3::ZERO, cell(max_neighbours), 0.5, 1.0),
+            1.0
+        );
+    }
+}
@@ -0,0 +1,389 @@
+//! Binary snapshots of an `AutomatonGrid`.
+//!
+//! All numbers are little endian. After the `CA3D` magic and a version byte
+//! come the size, the rule in its text notation, the boundary mode, seed,
+//! initial condition, color settings and generation, then the cell states as
+//! runs of `(length: u32, state: u8)`. Neighbour counts are not stored, they
+//! are recomputed from the alive cells on load.
+use crate::automaton_grid::{ActiveCells, AutomatonGrid, BoundaryMode};
+use crate::cell::Cells;
+use crate::color::{Color, ColorMethod};
+use crate::initial_condition::{InitialCondition, InitialShape};
+use crate::rule::{Rule, RuleNotationError};
+use glam::UVec3;
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"CA3D";
+const VERSION: u8 = 1;
+/// Largest number of cells a snapshot may hold, 256³ like the largest grid
+/// of the viewer.
+const MAX_CELLS: usize = 1 << 24;
+
+impl AutomatonGrid {
+    pub fn write_snapshot(&self, writer: &mut impl Write) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+        for len in self.size.to_array() {
+            writer.write_all(&len.to_le_bytes())?;
+        }
+        let rule = self.rule.to_string();
+        writer.write_all(&(rule.len() as u32).to_le_bytes())?;
+        writer.write_all(rule.as_bytes())?;
+        writer.write_all(&[boundary_mode_to_u8(self.boundary_mode)])?;
+        writer.write_all(&self.seed.to_le_bytes())?;
+        writer.write_all(&[initial_shape_to_u8(self.initial_condition.shape)])?;
+        writer.write_all(&self.initial_condition.radius.to_le_bytes())?;
+        writer.write_all(&self.initial_condition.density.to_le_bytes())?;
+        writer.write_all(&[color_method_to_u8(&self.color_method)])?;
+        for color in [self.color_1, self.color_2] {
+            for channel in color.to_array() {
+                writer.write_all(&channel.to_le_bytes())?;
+            }
+        }
+        writer.write_all(&self.generation.to_le_bytes())?;
+
+        let runs = state_runs(&self.cells.states);
+        writer.write_all(&(runs.len() as u32).to_le_bytes())?;
+        for (len, state) in runs {
+            writer.write_all(&len.to_le_bytes())?;
+            writer.write_all(&[state])?;
+        }
+        Ok(())
+    }
+
+    /// Reads a grid written by `write_snapshot`, it continues exactly where
+    /// the saved one was. `parallel` is left disabled.
+    pub fn read_snapshot(reader: &mut impl Read) -> Result<Self, SnapshotError> {
+        if &read_array::<4>(reader)? != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        let [version] = read_array(reader)?;
+        if version != VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+        let size = UVec3::new(read_u32(reader)?, read_u32(reader)?, read_u32(reader)?);
+        let len = size
+            .to_array()
+            .iter()
+            .try_fold(1usize, |len, axis| len.checked_mul(*axis as usize))
+            .filter(|len| (1..=MAX_CELLS).contains(len))
+            .ok_or(SnapshotError::Invalid("grid size"))?;
+
+        let rule_len = read_u32(reader)? as usize;
+        let mut rule = vec![];
+        reader.take(rule_len as u64).read_to_end(&mut rule)?;
+        let rule: Rule = String::from_utf8(rule)
+            .map_err(|_| SnapshotError::Invalid("rule"))?
+            .parse()?;
+
+        let [boundary_mode] = read_array(reader)?;
+        let boundary_mode =
+            boundary_mode_from_u8(boundary_mode).ok_or(SnapshotError::Invalid("boundary mode"))?;
+        let seed = u64::from_le_bytes(read_array(reader)?);
+        let [shape] = read_array(reader)?;
+        let initial_condition = InitialCondition {
+            shape: initial_shape_from_u8(shape).ok_or(SnapshotError::Invalid("initial shape"))?,
+            radius: read_u32(reader)?,
+            density: f32::from_le_bytes(read_array(reader)?),
+        };
+        let [color_method] = read_array(reader)?;
+        let color_method =
+            color_method_from_u8(color_method).ok_or(SnapshotError::Invalid("color method"))?;
+        let color_1 = read_color(reader)?;
+        let color_2 = read_color(reader)?;
+        let generation = u64::from_le_bytes(read_array(reader)?);
+
+        // the header is not trusted, so the states grow as the runs are read
+        let mut states = vec![];
+        for _ in 0..read_u32(reader)? {
+            let run = read_u32(reader)? as usize;
+            // any byte is a state, dying states above the rule's are left
+            // by lowering the state count and keep fading one step at a time
+            let [state] = read_array(reader)?;
+            if run > len - states.len() {
+                return Err(SnapshotError::Invalid("cell count"));
+            }
+            states.resize(states.len() + run, state);
+        }
+        if states.len() != len {
+            return Err(SnapshotError::Invalid("cell count"));
+        }
+
+        let mut grid = AutomatonGrid {
+            size,
+            cells: Cells::new(len, rule.get_max_neighbours()),
+            rule,
+            color_method,
+            color_1,
+            color_2,
+            seed,
+            initial_condition,
+            boundary_mode,
+            parallel: false,
+            generation,
+            active: ActiveCells::default(),
+        };
+        grid.cells.states = states;
+        grid.recount_neighbours();
+        for idx in 0..len {
+            if grid.cells.is_occupied(idx) {
+                grid.update_faces(idx);
+            }
+        }
+        Ok(grid)
+    }
+}
+
+/// Why a snapshot could not be read.
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    /// The data does not start with the snapshot magic.
+    NotASnapshot,
+    UnsupportedVersion(u8),
+    Rule(RuleNotationError),
+    /// A field holds a value no grid can have.
+    Invalid(&'static str),
+}
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "could not read snapshot, {err}"),
+            SnapshotError::NotASnapshot => write!(f, "not a snapshot file"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version {version}")
+            }
+            SnapshotError::Rule(err) => write!(f, "invalid rule in snapshot, {err}"),
+            SnapshotError::Invalid(field) => write!(f, "invalid {field} in snapshot"),
+        }
+    }
+}
+impl std::error::Error for SnapshotError {}
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> Self {
+        SnapshotError::Io(err)
+    }
+}
+impl From<RuleNotationError> for SnapshotError {
+    fn from(err: RuleNotationError) -> Self {
+        SnapshotError::Rule(err)
+    }
+}
+
+/// Run-length encodes the packed states, runs are split at `u32::MAX`.
+fn state_runs(states: &[u8]) -> Vec<(u32, u8)> {
+    let mut runs: Vec<(u32, u8)> = vec![];
+    for state in states {
+        match runs.last_mut() {
+            Some((len, last)) if last == state && *len < u32::MAX => *len += 1,
+            _ => runs.push((1, *state)),
+        }
+    }
+    runs
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn read_u32(reader: &mut impl Read) -> io::Result<u32> {
+    Ok(u32::from_le_bytes(read_array(reader)?))
+}
+
+fn read_color(reader: &mut impl Read) -> io::Result<Color> {
+    let mut channels = [0.0; 4];
+    for channel in &mut channels {
+        *channel = f32::from_le_bytes(read_array(reader)?);
+    }
+    let [red, green, blue, alpha] = channels;
+    Ok(Color {
+        red,
+        green,
+        blue,
+        alpha,
+    })
+}
+
+fn boundary_mode_to_u8(mode: BoundaryMode) -> u8 {
+    match mode {
+        BoundaryMode::Toroidal => 0,
+        BoundaryMode::Dead => 1,
+        BoundaryMode::Reflective => 2,
+        BoundaryMode::Clamped => 3,
+    }
+}
+
+fn boundary_mode_from_u8(mode: u8) -> Option<BoundaryMode> {
+    Some(match mode {
+        0 => BoundaryMode::Toroidal,
+        1 => BoundaryMode::Dead,
+        2 => BoundaryMode::Reflective,
+        3 => BoundaryMode::Clamped,
+        _ => return None,
+    })
+}
+
+fn initial_shape_to_u8(shape: InitialShape) -> u8 {
+    match shape {
+        InitialShape::NoiseCube => 0,
+        InitialShape::NoiseSphere => 1,
+        InitialShape::SingleCell => 2,
+        InitialShape::HollowShell => 3,
+        InitialShape::RandomFill => 4,
+        InitialShape::SymmetricNoise => 5,
+    }
+}
+
+fn initial_shape_from_u8(shape: u8) -> Option<InitialShape> {
+    Some(match shape {
+        0 => InitialShape::NoiseCube,
+        1 => InitialShape::NoiseSphere,
+        2 => InitialShape::SingleCell,
+        3 => InitialShape::HollowShell,
+        4 => InitialShape::RandomFill,
+        5 => InitialShape::SymmetricNoise,
+        _ => return None,
+    })
+}
+
+fn color_method_to_u8(method: &ColorMethod) -> u8 {
+    match method {
+        ColorMethod::DistToCenter => 0,
+        ColorMethod::StateLerp => 1,
+        ColorMethod::Neighbour => 2,
+    }
+}
+
+fn color_method_from_u8(method: u8) -> Option<ColorMethod> {
+    Some(match method {
+        0 => ColorMethod::DistToCenter,
+        1 => ColorMethod::StateLerp,
+        2 => ColorMethod::Neighbour,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell::CellState;
+
+    fn round_trip(grid: &AutomatonGrid) -> AutomatonGrid {
+        let mut bytes = vec![];
+        grid.write_snapshot(&mut bytes).unwrap();
+        AutomatonGrid::read_snapshot(&mut bytes.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn runs_are_compact() {
+        assert_eq!(
+            state_runs(&[0, 0, 0, 255, 3, 3]),
+            [(3, 0), (1, 255), (2, 3)]
+        );
+        assert!(state_runs(&[]).is_empty());
+    }
+
+    #[test]
+    fn loaded_grid_evolves_identically() {
+        for (rule, mode) in [
+            ("4/4/5/M", BoundaryMode::Toroidal),
+            ("2,6,9/4,6,8-10/10/M", BoundaryMode::Reflective),
+            ("0-6/1,3/2/N2", BoundaryMode::Dead),
+        ] {
+            let mut grid = AutomatonGrid {
+                rule: rule.parse().unwrap(),
+                boundary_mode: mode,
+                seed: 9,
+                color_method: ColorMethod::StateLerp,
+                ..Default::default()
+            };
+            grid.set_size(UVec3::new(24, 18, 12));
+            for _ in 0..15 {
+                grid.update();
+            }
+
+            let mut loaded = round_trip(&grid);
+            assert_eq!(loaded.size, grid.size);
+            assert_eq!(loaded.rule, grid.rule);
+            assert_eq!(loaded.color_method, grid.color_method);
+            assert_eq!(loaded.color_2, grid.color_2);
+            assert_eq!(loaded.generation, 15);
+            assert!(loaded.cells == grid.cells, "{rule} neighbours differ");
+
+            for step in 0..20 {
+                grid.update();
+                loaded.update();
+                assert!(loaded.cells == grid.cells, "{rule} diverged at step {step}");
+            }
+            assert_eq!(loaded.generation, grid.generation);
+        }
+    }
+
+    #[test]
+    fn keeps_dying_states_above_the_rule() {
+        let mut grid = AutomatonGrid {
+            rule: "4/4/10/M".parse().unwrap(),
+            seed: 4,
+            ..Default::default()
+        };
+        grid.set_size(UVec3::splat(16));
+        for _ in 0..5 {
+            grid.update();
+        }
+        // what the States slider does, without a reset
+        grid.rule.states = 3;
+        assert!(grid
+            .cells
+            .iter()
+            .any(|cell| cell.state == CellState::Dying(5)));
+
+        let mut loaded = round_trip(&grid);
+        assert!(loaded.cells == grid.cells);
+        for step in 0..10 {
+            grid.update();
+            loaded.update();
+            assert!(loaded.cells == grid.cells, "diverged at step {step}");
+        }
+    }
+
+    #[test]
+    fn snapshots_are_smaller_than_the_grid() {
+        let grid = AutomatonGrid::default();
+        let mut bytes = vec![];
+        grid.write_snapshot(&mut bytes).unwrap();
+        assert!(bytes.len() < grid.cells.len() / 4);
+    }
+
+    #[test]
+    fn rejects_bad_snapshots() {
+        let mut bytes = vec![];
+        AutomatonGrid::default().write_snapshot(&mut bytes).unwrap();
+
+        assert!(matches!(
+            AutomatonGrid::read_snapshot(&mut &b"nope"[..]),
+            Err(SnapshotError::NotASnapshot)
+        ));
+        let mut newer = bytes.clone();
+        newer[4] = VERSION + 1;
+        assert!(matches!(
+            AutomatonGrid::read_snapshot(&mut newer.as_slice()),
+            Err(SnapshotError::UnsupportedVersion(_))
+        ));
+        let mut too_large = bytes.clone();
+        for (axis, len) in [256u32, 256, 257].into_iter().enumerate() {
+            too_large[5 + axis * 4..9 + axis * 4].copy_from_slice(&len.to_le_bytes());
+        }
+        assert!(matches!(
+            AutomatonGrid::read_snapshot(&mut too_large.as_slice()),
+            Err(SnapshotError::Invalid("grid size"))
+        ));
+        let truncated = &bytes[..bytes.len() - 1];
+        assert!(matches!(
+            AutomatonGrid::read_snapshot(&mut &truncated[..]),
+            Err(SnapshotError::Io(_))
+        ));
+    }
+}
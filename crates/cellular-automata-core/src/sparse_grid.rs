@@ -0,0 +1,477 @@
+use crate::automaton_grid::{step_cell, Change, Example};
+use crate::cell::{Cell, CellState, Cells};
+use crate::color::{Color, ColorMethod};
+use crate::grid::Grid;
+use crate::initial_condition::InitialCondition;
+use crate::rule::Rule;
+use glam::{IVec3, UVec3};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use std::collections::{BTreeMap, HashMap};
+
+/// Cells along each axis of a chunk.
+pub const CHUNK_SIZE: i32 = 16;
+const CHUNK_LEN: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// Unbounded grid made of 16³ chunks, a chunk is allocated when a cell in it
+/// gains a neighbour and freed once it has no alive, dying or counted cells.
+///
+/// Empty cells without neighbours are never born, even when the rule has 0
+/// in its birth counts, as that would fill the whole space.
+#[derive(Debug)]
+#[cfg_attr(feature = "bevy", derive(bevy_ecs::system::Resource))]
+pub struct SparseGrid {
+    pub rule: Rule,
+    pub color_method: ColorMethod,
+    pub color_1: Color,
+    pub color_2: Color,
+    /// Seed of the initial noise, the same seed and rule always give the same evolution.
+    pub seed: u64,
+    /// Spawned around the origin, `RandomFill` fills a cube of side `2 * radius + 1`.
+    pub initial_condition: InitialCondition,
+    /// Steps taken since the last `reset()`.
+    pub generation: u64,
+    chunks: HashMap<IVec3, Chunk>,
+    extent: Extent,
+}
+
+impl SparseGrid {
+    pub fn new(rule: Rule, seed: u64) -> Self {
+        let mut grid = Self {
+            rule,
+            color_method: ColorMethod::default(),
+            color_1: Color::srgb(1., 1., 0.),
+            color_2: Color::srgb(1., 0., 0.),
+            seed,
+            initial_condition: InitialCondition::default(),
+            generation: 0,
+            chunks: HashMap::new(),
+            extent: Extent::default(),
+        };
+        grid.spawn_initial_condition();
+        grid
+    }
+
+    pub fn reset(&mut self) {
+        self.chunks.clear();
+        self.extent = Extent::default();
+        self.spawn_initial_condition();
+        self.generation = 0;
+    }
+
+    /// Counts the neighbours of every cell again, for after changing the
+    /// neighbour method or radius of `rule` without a reset.
+    pub fn recount_neighbours(&mut self) {
+        let max_neighbours = self.rule.get_max_neighbours();
+        let mut alive = vec![];
+        for (key, chunk) in &mut self.chunks {
+            chunk.cells.clear_neighbours(max_neighbours);
+            chunk.live = 0;
+            for idx in 0..CHUNK_LEN {
+                if chunk.cells.is_occupied(idx) {
+                    chunk.live += 1;
+                }
+                if chunk.cells.state(idx) == CellState::Alive {
+                    alive.push(*key * CHUNK_SIZE + local_pos(idx));
+                }
+            }
+        }
+        self.chunks.retain(|_, chunk| chunk.live > 0);
+        for pos in alive {
+            self.update_neighbours(pos, true);
+        }
+    }
+
+    pub fn set_example(&mut self, example: Example) {
+        self.rule = example.rule;
+        self.color_method = example.color_method;
+        self.color_1 = example.color_1;
+        self.color_2 = example.color_2;
+        self.reset();
+    }
+
+    pub fn set_seed(&mut self, new_seed: u64) {
+        if self.seed != new_seed {
+            self.seed = new_seed;
+            self.reset();
+        }
+    }
+
+    /// Number of chunks currently allocated.
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    fn spawn_initial_condition(&mut self) {
+        let mut rand = ChaCha8Rng::seed_from_u64(self.seed);
+        let size = UVec3::splat(self.initial_condition.radius * 2 + 1);
+        let center = size.as_ivec3() / 2;
+        let positions = self.initial_condition.generate(center, size, &mut rand);
+
+        for pos in positions {
+            self.spawn(pos - center);
+        }
+    }
+
+    /// Makes an empty cell alive and counts it in its neighbours, leaves
+    /// alive or dying cells as they are.
+    fn spawn(&mut self, pos: IVec3) {
+        let max_neighbours = self.rule.get_max_neighbours();
+        let (key, idx) = chunk_idx(pos);
+        let chunk = self
+            .chunks
+            .entry(key)
+            .or_insert_with(|| Chunk::new(max_neighbours));
+        if !chunk.cells.is_occupied(idx) {
+            let could_change = chunk.cells.could_change(idx);
+            chunk.cells.set_state(idx, CellState::Alive);
+            chunk.recount(idx, could_change);
+            self.extent.update(pos, true);
+            self.update_neighbours(pos, true);
+        }
+    }
+
+    fn update_neighbours(&mut self, pos: IVec3, increase: bool) {
+        let max_neighbours = self.rule.get_max_neighbours();
+        for dir in self.rule.get_neighbour_iter() {
+            let (key, idx) = chunk_idx(pos - *dir);
+            let chunk = if increase {
+                self.chunks
+                    .entry(key)
+                    .or_insert_with(|| Chunk::new(max_neighbours))
+            } else {
+                // a cell that loses a neighbour has counted it, so its chunk exists
+                match self.chunks.get_mut(&key) {
+                    Some(chunk) => chunk,
+                    None => continue,
+                }
+            };
+            let could_change = chunk.cells.could_change(idx);
+            chunk.cells.neighbours.update(idx, increase);
+            chunk.recount(idx, could_change);
+            // losing neighbours never brings a chunk back, so it can go now
+            if chunk.live == 0 {
+                self.chunks.remove(&key);
+            }
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.generation += 1;
+        let mut spawns = vec![];
+        let mut deaths = vec![];
+        let mut toggles = vec![];
+        let mut emptied = vec![];
+
+        for (key, chunk) in &mut self.chunks {
+            let origin = *key * CHUNK_SIZE;
+            let Chunk { cells, live } = chunk;
+            for idx in 0..CHUNK_LEN {
+                if !cells.could_change(idx) {
+                    continue;
+                }
+                let was_occupied = cells.is_occupied(idx);
+                let neighbours = cells.neighbours(idx);
+                match step_cell(&self.rule, &mut cells.states[idx], neighbours) {
+                    Some(Change::Spawn) => spawns.push(origin + local_pos(idx)),
+                    Some(Change::Death) => deaths.push(origin + local_pos(idx)),
+                    None => {}
+                }
+                if was_occupied != cells.is_occupied(idx) {
+                    toggles.push((origin + local_pos(idx), !was_occupied));
+                }
+                if !cells.could_change(idx) {
+                    *live -= 1;
+                }
+            }
+            if *live == 0 {
+                emptied.push(*key);
+            }
+        }
+        for (pos, occupied) in toggles {
+            self.extent.update(pos, occupied);
+        }
+        for pos in spawns {
+            self.update_neighbours(pos, true);
+        }
+        for pos in deaths {
+            self.update_neighbours(pos, false);
+        }
+        // the spawns may have counted cells in chunks that were emptied
+        for key in emptied {
+            if self.chunks.get(&key).is_some_and(|chunk| chunk.live == 0) {
+                self.chunks.remove(&key);
+            }
+        }
+    }
+
+    pub fn cell(&self, pos: IVec3) -> Cell {
+        let (key, idx) = chunk_idx(pos);
+        self.chunks
+            .get(&key)
+            .map(|chunk| chunk.cells.get(idx))
+            .unwrap_or_default()
+    }
+
+    /// Positions and copies of every alive or dying cell, in no particular order.
+    pub fn iter_occupied(&self) -> impl Iterator<Item = (IVec3, Cell)> + '_ {
+        self.chunks.iter().flat_map(|(key, chunk)| {
+            let origin = *key * CHUNK_SIZE;
+            (0..CHUNK_LEN)
+                .filter(|idx| chunk.cells.is_occupied(*idx))
+                .map(move |idx| (origin + local_pos(idx), chunk.cells.get(idx)))
+        })
+    }
+
+    /// Smallest and one past the largest position of the occupied cells,
+    /// the origin alone when there are none.
+    pub fn bounding_box(&self) -> (IVec3, IVec3) {
+        self.extent.bounding_box()
+    }
+}
+impl Default for SparseGrid {
+    fn default() -> Self {
+        Self::new(Rule::default(), rand::random())
+    }
+}
+impl Grid for SparseGrid {
+    fn rule(&self) -> &Rule {
+        &self.rule
+    }
+
+    fn update(&mut self) {
+        self.update();
+    }
+
+    fn reset(&mut self) {
+        self.reset();
+    }
+
+    fn cell(&self, pos: IVec3) -> Cell {
+        self.cell(pos)
+    }
+
+    fn occupied(&self) -> Box<dyn Iterator<Item = (IVec3, Cell)> + '_> {
+        Box::new(self.iter_occupied())
+    }
+
+    fn bounding_box(&self) -> (IVec3, IVec3) {
+        self.bounding_box()
+    }
+
+    fn color_method(&self) -> &ColorMethod {
+        &self.color_method
+    }
+
+    fn colors(&self) -> (Color, Color) {
+        (self.color_1, self.color_2)
+    }
+}
+
+/// The cells of a chunk and how many of them may change.
+#[derive(Debug)]
+struct Chunk {
+    cells: Cells,
+    /// Cells that are alive, dying or have a neighbour, the chunk is freed
+    /// once there are none.
+    live: u32,
+}
+impl Chunk {
+    fn new(max_neighbours: u16) -> Self {
+        Self {
+            cells: Cells::new(CHUNK_LEN, max_neighbours),
+            live: 0,
+        }
+    }
+
+    /// Updates `live` after a change to the cell at `idx`, `could_change`
+    /// being whether it could change before.
+    fn recount(&mut self, idx: usize, could_change: bool) {
+        match (could_change, self.cells.could_change(idx)) {
+            (false, true) => self.live += 1,
+            (true, false) => self.live -= 1,
+            _ => {}
+        }
+    }
+}
+
+/// How many alive or dying cells lie in each plane across the X, Y and Z
+/// axes, so the bounding box follows the cells without scanning them.
+#[derive(Debug, Default)]
+struct Extent([BTreeMap<i32, u32>; 3]);
+impl Extent {
+    /// Counts the cell at `pos` if it became occupied, or removes it if it
+    /// became empty.
+    fn update(&mut self, pos: IVec3, occupied: bool) {
+        for (planes, coord) in self.0.iter_mut().zip(pos.to_array()) {
+            if occupied {
+                *planes.entry(coord).or_default() += 1;
+            } else if let Some(count) = planes.get_mut(&coord) {
+                *count -= 1;
+                if *count == 0 {
+                    planes.remove(&coord);
+                }
+            }
+        }
+    }
+
+    fn bounding_box(&self) -> (IVec3, IVec3) {
+        let mut min = IVec3::ZERO;
+        let mut max = IVec3::ONE;
+        for (axis, planes) in self.0.iter().enumerate() {
+            if let (Some((first, _)), Some((last, _))) =
+                (planes.first_key_value(), planes.last_key_value())
+            {
+                min[axis] = *first;
+                max[axis] = *last + 1;
+            }
+        }
+        (min, max)
+    }
+}
+
+/// Key of the chunk holding `pos` and the index of `pos` inside of it.
+fn chunk_idx(pos: IVec3) -> (IVec3, usize) {
+    let key = pos.div_euclid(IVec3::splat(CHUNK_SIZE));
+    let local = pos - key * CHUNK_SIZE;
+    let idx = local.x + local.y * CHUNK_SIZE + local.z * CHUNK_SIZE * CHUNK_SIZE;
+    (key, idx as usize)
+}
+
+fn local_pos(idx: usize) -> IVec3 {
+    let idx = idx as i32;
+    IVec3::new(
+        idx % CHUNK_SIZE,
+        idx / CHUNK_SIZE % CHUNK_SIZE,
+        idx / CHUNK_SIZE / CHUNK_SIZE,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton_grid::{AutomatonGrid, BoundaryMode};
+    use crate::initial_condition::InitialShape;
+    use crate::rule::Indexes;
+    use std::collections::HashSet;
+
+    #[test]
+    fn chunk_idx_round_trip() {
+        for pos in [
+            IVec3::ZERO,
+            IVec3::new(15, 16, -1),
+            IVec3::new(-17, 33, -32),
+        ] {
+            let (key, idx) = chunk_idx(pos);
+            assert!(idx < CHUNK_LEN);
+            assert_eq!(key * CHUNK_SIZE + local_pos(idx), pos);
+        }
+        assert_eq!(chunk_idx(IVec3::splat(-1)).0, IVec3::splat(-1));
+    }
+
+    #[test]
+    fn matches_a_large_dead_bounded_grid() {
+        let rule: Rule = "4/4/5/M".parse().unwrap();
+        let mut sparse = SparseGrid::new(rule.clone(), 3);
+        sparse.initial_condition.radius = 4;
+        sparse.reset();
+
+        let mut bounded = AutomatonGrid {
+            rule,
+            seed: 3,
+            boundary_mode: BoundaryMode::Dead,
+            ..Default::default()
+        };
+        bounded.initial_condition.radius = 4;
+        bounded.set_size(UVec3::splat(40));
+
+        // the pattern grows at most a cell per step, so it never reaches the faces
+        for _ in 0..12 {
+            let sparse_cells: HashSet<(IVec3, Cell)> = sparse.iter_occupied().collect();
+            let bounded_cells: HashSet<(IVec3, Cell)> = bounded
+                .occupied()
+                .map(|(pos, cell)| (pos - bounded.center(), cell))
+                .collect();
+            assert_eq!(sparse_cells, bounded_cells);
+
+            sparse.update();
+            bounded.update();
+        }
+    }
+
+    #[test]
+    fn empty_chunks_are_freed() {
+        let rule = Rule {
+            survival_rule: Indexes::new(&[]),
+            birth_rule: Indexes::new(&[]),
+            states: 3,
+            ..Default::default()
+        };
+        let mut grid = SparseGrid::new(rule, 0);
+        grid.initial_condition.shape = InitialShape::SingleCell;
+        grid.reset();
+        // the origin sits on a chunk corner, its neighbours span 8 chunks
+        assert_eq!(grid.chunk_count(), 8);
+        assert_eq!(grid.bounding_box(), (IVec3::ZERO, IVec3::ONE));
+
+        grid.update();
+        assert_eq!(grid.cell(IVec3::ZERO).state, CellState::Dying(1));
+        grid.update();
+        assert_eq!(grid.iter_occupied().count(), 0);
+        assert_eq!(grid.chunk_count(), 0);
+    }
+
+    #[test]
+    fn recount_after_neighbourhood_change() {
+        let mut grid = SparseGrid::new("4/4/5/M".parse().unwrap(), 2);
+        grid.initial_condition.radius = 5;
+        grid.reset();
+        for _ in 0..3 {
+            grid.update();
+        }
+        grid.rule = "5,6/1-3/15/N2".parse().unwrap();
+        grid.recount_neighbours();
+        for _ in 0..2 {
+            let (min, max) = grid.bounding_box();
+            let reach = IVec3::splat(grid.rule.get_neighbour_reach());
+            for z in min.z - reach.z..max.z + reach.z {
+                for y in min.y - reach.y..max.y + reach.y {
+                    for x in min.x - reach.x..max.x + reach.x {
+                        let pos = IVec3::new(x, y, z);
+                        let alive = grid
+                            .rule
+                            .get_neighbour_iter()
+                            .iter()
+                            .filter(|dir| grid.cell(pos + **dir).state == CellState::Alive)
+                            .count();
+                        assert_eq!(grid.cell(pos).neighbours as usize, alive, "{pos}");
+                    }
+                }
+            }
+            grid.update();
+        }
+    }
+
+    #[test]
+    fn chunk_counts_and_bounds_follow_the_cells() {
+        let mut grid = SparseGrid::new("4/4/5/M".parse().unwrap(), 5);
+        grid.initial_condition.radius = 6;
+        grid.reset();
+
+        for step in 0..30 {
+            for (key, chunk) in &grid.chunks {
+                let live = (0..CHUNK_LEN).filter(|idx| chunk.cells.could_change(*idx));
+                assert_eq!(chunk.live as usize, live.count(), "{key} at step {step}");
+                assert!(chunk.live > 0, "{key} kept empty at step {step}");
+            }
+            let scanned = grid
+                .iter_occupied()
+                .map(|(pos, _)| (pos, pos + IVec3::ONE))
+                .reduce(|(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)))
+                .unwrap_or((IVec3::ZERO, IVec3::ONE));
+            assert_eq!(grid.bounding_box(), scanned, "step {step}");
+
+            grid.update();
+        }
+    }
+}
@@ -0,0 +1,422 @@
+//! MagicaVoxel `.vox` models.
+//!
+//! Only the `SIZE`, `XYZI` and `RGBA` chunks are written, and only the first
+//! model of a file is read. MagicaVoxel is Z up while the grid is Y up, both
+//! right-handed, so models are rotated a quarter turn about X on the way in
+//! and out.
+use crate::automaton_grid::AutomatonGrid;
+use crate::cell::Cells;
+use crate::color::{lerp_color, Color};
+use crate::grid::Grid;
+use glam::UVec3;
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+
+const MAGIC: &[u8; 4] = b"VOX ";
+const VERSION: u32 = 150;
+/// Largest side of a model the format can hold.
+pub const MAX_SIZE: u32 = 256;
+/// Colors of a palette, color index 0 stands for an empty voxel.
+pub const PALETTE_LEN: usize = 255;
+
+/// The first model of a `.vox` file, in grid axes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VoxModel {
+    pub size: UVec3,
+    /// Positions and color indexes, from 1 to 255, of the filled voxels.
+    pub voxels: Vec<(UVec3, u8)>,
+    /// The color of index `i` is at `i - 1`, `None` if the file uses the
+    /// MagicaVoxel default palette.
+    pub palette: Option<Vec<Color>>,
+}
+
+/// What to do with a model that does not fit in the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VoxFit {
+    /// Drop the voxels outside of the grid.
+    #[default]
+    Crop,
+    /// Grow the grid along the axes the model does not fit in.
+    Resize,
+}
+
+impl AutomatonGrid {
+    /// Clears the grid and spawns a cell for every voxel of `model`, with the
+    /// model centered on `center()`. `reset()` goes back to the initial condition.
+    pub fn stamp_vox(&mut self, model: &VoxModel, fit: VoxFit) {
+        if fit == VoxFit::Resize {
+            self.size = self.size.max(model.size);
+        }
+        self.cells = Cells::new(
+            self.size.element_product() as usize,
+            self.rule.get_max_neighbours(),
+        );
+        self.invalidate_active_cells();
+        self.generation = 0;
+
+        let offset = self.center() - (model.size / 2).as_ivec3();
+        for (pos, _) in &model.voxels {
+            let pos = offset + pos.as_ivec3();
+            if self.in_bounds(pos) {
+                self.spawn(self.pos_to_idx(pos));
+            }
+        }
+    }
+}
+
+/// Writes the alive and dying cells of `grid` as a `.vox` model, colored by a
+/// palette of 255 steps between color A and B of its color method.
+pub fn write_vox<G: Grid + ?Sized>(grid: &G, writer: &mut impl Write) -> Result<(), VoxError> {
+    let (min, max) = grid.bounding_box();
+    let size = (max - min).as_uvec3();
+    if size.cmpgt(UVec3::splat(MAX_SIZE)).any() {
+        return Err(VoxError::TooLarge(size));
+    }
+
+    let mut voxels = vec![];
+    for (pos, cell) in grid.occupied() {
+        let blend = grid.color_method().get_blend(grid, pos, cell);
+        let color_idx = (blend * (PALETTE_LEN - 1) as f32).round() as u8 + 1;
+        voxels.push((grid_to_vox((pos - min).as_uvec3(), size), color_idx));
+    }
+
+    let mut size_chunk = vec![];
+    for len in swap_size(size).to_array() {
+        size_chunk.extend(len.to_le_bytes());
+    }
+    let mut xyzi_chunk = (voxels.len() as u32).to_le_bytes().to_vec();
+    for (pos, color_idx) in voxels {
+        xyzi_chunk.extend([pos.x as u8, pos.y as u8, pos.z as u8, color_idx]);
+    }
+    let (color_1, color_2) = grid.colors();
+    let mut rgba_chunk = vec![];
+    for color_idx in 0..=PALETTE_LEN {
+        let dt = color_idx as f32 / (PALETTE_LEN - 1) as f32;
+        let color = lerp_color(color_1, color_2, dt).to_array();
+        rgba_chunk.extend(color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8));
+    }
+
+    let mut children = vec![];
+    write_chunk(&mut children, b"SIZE", &size_chunk)?;
+    write_chunk(&mut children, b"XYZI", &xyzi_chunk)?;
+    write_chunk(&mut children, b"RGBA", &rgba_chunk)?;
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(b"MAIN")?;
+    writer.write_all(&0u32.to_le_bytes())?;
+    writer.write_all(&(children.len() as u32).to_le_bytes())?;
+    writer.write_all(&children)?;
+    Ok(())
+}
+
+pub fn read_vox(reader: &mut impl Read) -> Result<VoxModel, VoxError> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    let mut data = data.as_slice();
+    if take(&mut data, 4)? != MAGIC {
+        return Err(VoxError::NotAVox);
+    }
+    take(&mut data, 4)?; // version
+    let (id, _, children) = read_chunk(&mut data)?;
+    if id != b"MAIN" {
+        return Err(VoxError::Invalid("MAIN chunk"));
+    }
+    let mut children = take(&mut data, children)?;
+
+    let mut size = None;
+    let mut voxels = None;
+    let mut palette = None;
+    while !children.is_empty() {
+        let (id, mut content, _) = read_chunk(&mut children)?;
+        match id {
+            b"SIZE" if size.is_none() => {
+                let vox_size = UVec3::new(
+                    read_u32(&mut content)?,
+                    read_u32(&mut content)?,
+                    read_u32(&mut content)?,
+                );
+                if vox_size.cmpeq(UVec3::ZERO).any() || vox_size.cmpgt(UVec3::splat(MAX_SIZE)).any()
+                {
+                    return Err(VoxError::Invalid("SIZE chunk"));
+                }
+                size = Some(vox_size);
+            }
+            b"XYZI" if voxels.is_none() => {
+                let count = read_u32(&mut content)? as usize;
+                let bytes = take(&mut content, count * 4)?;
+                let model_voxels = bytes
+                    .chunks_exact(4)
+                    .map(|voxel| {
+                        let pos = UVec3::new(voxel[0] as u32, voxel[1] as u32, voxel[2] as u32);
+                        (pos, voxel[3])
+                    })
+                    .collect::<Vec<_>>();
+                voxels = Some(model_voxels);
+            }
+            b"RGBA" => {
+                let bytes = take(&mut content, (PALETTE_LEN + 1) * 4)?;
+                let colors = bytes[..PALETTE_LEN * 4]
+                    .chunks_exact(4)
+                    .map(|rgba| Color {
+                        red: rgba[0] as f32 / 255.0,
+                        green: rgba[1] as f32 / 255.0,
+                        blue: rgba[2] as f32 / 255.0,
+                        alpha: rgba[3] as f32 / 255.0,
+                    })
+                    .collect();
+                palette = Some(colors);
+            }
+            _ => {}
+        }
+    }
+
+    let size = size.ok_or(VoxError::Invalid("SIZE chunk"))?;
+    let voxels = voxels.ok_or(VoxError::Invalid("XYZI chunk"))?;
+    if voxels.iter().any(|(pos, _)| pos.cmpge(size).any()) {
+        return Err(VoxError::Invalid("voxel position"));
+    }
+    Ok(VoxModel {
+        size: swap_size(size),
+        voxels: voxels
+            .into_iter()
+            .map(|(pos, color_idx)| (vox_to_grid(pos, size), color_idx))
+            .collect(),
+        palette,
+    })
+}
+
+/// Why a model could not be written or read.
+#[derive(Debug)]
+pub enum VoxError {
+    Io(io::Error),
+    /// The data does not start with the `.vox` magic.
+    NotAVox,
+    /// The cells span more than 256 voxels along some axis.
+    TooLarge(UVec3),
+    /// A chunk is missing, truncated or holds a value no model can have.
+    Invalid(&'static str),
+}
+impl Display for VoxError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VoxError::Io(err) => write!(f, "could not read or write model, {err}"),
+            VoxError::NotAVox => write!(f, "not a .vox file"),
+            VoxError::TooLarge(size) => write!(
+                f,
+                "a {}x{}x{} model does not fit in .vox, which allows up to {MAX_SIZE} per axis",
+                size.x, size.y, size.z
+            ),
+            VoxError::Invalid(field) => write!(f, "invalid {field} in .vox file"),
+        }
+    }
+}
+impl std::error::Error for VoxError {}
+impl From<io::Error> for VoxError {
+    fn from(err: io::Error) -> Self {
+        VoxError::Io(err)
+    }
+}
+
+/// Model size in the other axes, the same both ways.
+fn swap_size(size: UVec3) -> UVec3 {
+    UVec3::new(size.x, size.z, size.y)
+}
+
+/// Voxel position of the grid position `pos` of a model of grid size `size`:
+/// grid +Y goes to `.vox` +Z and grid +Z to `.vox` -Y.
+fn grid_to_vox(pos: UVec3, size: UVec3) -> UVec3 {
+    UVec3::new(pos.x, size.z - 1 - pos.z, pos.y)
+}
+
+/// Inverse of `grid_to_vox`, `size` being the `.vox` model size.
+fn vox_to_grid(pos: UVec3, size: UVec3) -> UVec3 {
+    UVec3::new(pos.x, pos.z, size.y - 1 - pos.y)
+}
+
+fn write_chunk(writer: &mut impl Write, id: &[u8; 4], content: &[u8]) -> io::Result<()> {
+    writer.write_all(id)?;
+    writer.write_all(&(content.len() as u32).to_le_bytes())?;
+    writer.write_all(&0u32.to_le_bytes())?;
+    writer.write_all(content)
+}
+
+/// Id, content and children size of the chunk at the start of `data`.
+fn read_chunk<'a>(data: &mut &'a [u8]) -> Result<(&'a [u8], &'a [u8], usize), VoxError> {
+    let id = take(data, 4)?;
+    let content = read_u32(data)? as usize;
+    let children = read_u32(data)? as usize;
+    Ok((id, take(data, content)?, children))
+}
+
+fn read_u32(data: &mut &[u8]) -> Result<u32, VoxError> {
+    let bytes = take(data, 4)?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn take<'a>(data: &mut &'a [u8], len: usize) -> Result<&'a [u8], VoxError> {
+    if data.len() < len {
+        return Err(VoxError::Invalid("chunk length"));
+    }
+    let (taken, rest) = data.split_at(len);
+    *data = rest;
+    Ok(taken)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::automaton_grid::BoundaryMode;
+    use crate::cell::CellState;
+    use crate::color::ColorMethod;
+    use glam::IVec3;
+    use std::collections::HashMap;
+
+    #[test]
+    fn exported_model_parses_back() {
+        let mut grid = AutomatonGrid {
+            seed: 4,
+            color_method: ColorMethod::StateLerp,
+            ..Default::default()
+        };
+        grid.set_size(UVec3::new(20, 16, 12));
+        for _ in 0..5 {
+            grid.update();
+        }
+
+        let mut bytes = vec![];
+        write_vox(&grid, &mut bytes).unwrap();
+        let model = read_vox(&mut bytes.as_slice()).unwrap();
+
+        assert_eq!(model.size, grid.size);
+        let occupied: HashMap<UVec3, _> = grid
+            .occupied()
+            .map(|(pos, cell)| (pos.as_uvec3(), cell))
+            .collect();
+        assert_eq!(model.voxels.len(), occupied.len());
+        for (pos, color_idx) in &model.voxels {
+            let cell = occupied[pos];
+            let blend = grid.color_method.get_blend(&grid, pos.as_ivec3(), cell);
+            assert_eq!(*color_idx, (blend * 254.0).round() as u8 + 1);
+        }
+
+        let palette = model.palette.unwrap();
+        assert_eq!(palette.len(), PALETTE_LEN);
+        assert_eq!(palette[0], Color::srgb(1., 1., 0.));
+        assert_eq!(palette[PALETTE_LEN - 1], Color::srgb(1., 0., 0.));
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(matches!(
+            read_vox(&mut &b"CA3D\x01"[..]),
+            Err(VoxError::NotAVox)
+        ));
+        assert!(matches!(
+            read_vox(&mut &b"VOX \x96\0\0\0MAIN\0\0\0\0\x40\0\0\0"[..]),
+            Err(VoxError::Invalid(_))
+        ));
+
+        // sizes no grid should be grown to, with an otherwise valid model
+        for size in [[2000, 2000, 2000], [0, 4, 4], [4, 4, MAX_SIZE + 1]] {
+            let mut children = vec![];
+            let size_content: Vec<u8> = size.iter().flat_map(|side| side.to_le_bytes()).collect();
+            write_chunk(&mut children, b"SIZE", &size_content).unwrap();
+            write_chunk(&mut children, b"XYZI", &0u32.to_le_bytes()).unwrap();
+            let mut data = b"VOX \x96\0\0\0MAIN\0\0\0\0".to_vec();
+            data.extend((children.len() as u32).to_le_bytes());
+            data.extend(children);
+            assert!(
+                matches!(
+                    read_vox(&mut data.as_slice()),
+                    Err(VoxError::Invalid("SIZE chunk"))
+                ),
+                "{size:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn axes_are_rotated_not_mirrored() {
+        let size = UVec3::new(4, 5, 6);
+        let origin = UVec3::ONE;
+        let dir = |axis: UVec3| {
+            grid_to_vox(origin + axis, size).as_ivec3() - grid_to_vox(origin, size).as_ivec3()
+        };
+        let (x, y, z) = (dir(UVec3::X), dir(UVec3::Y), dir(UVec3::Z));
+        assert_eq!(x, IVec3::X);
+        // grid up is .vox up
+        assert_eq!(y, IVec3::Z);
+        assert_eq!(x.cross(y), z);
+
+        for pos in [UVec3::ZERO, origin, size - 1, UVec3::new(3, 0, 2)] {
+            assert_eq!(vox_to_grid(grid_to_vox(pos, size), swap_size(size)), pos);
+        }
+    }
+
+    fn cube_model(side: u32) -> VoxModel {
+        let voxels = (0..side.pow(3))
+            .map(|i| (UVec3::new(i % side, i / side % side, i / side / side), 1))
+            .collect();
+        VoxModel {
+            size: UVec3::splat(side),
+            voxels,
+            palette: None,
+        }
+    }
+
+    #[test]
+    fn stamped_model_counts_neighbours() {
+        for mode in [BoundaryMode::Toroidal, BoundaryMode::Dead] {
+            let mut grid = AutomatonGrid {
+                boundary_mode: mode,
+                ..Default::default()
+            };
+            grid.set_size(UVec3::new(10, 8, 6));
+            grid.stamp_vox(&cube_model(5), VoxFit::Crop);
+
+            assert_eq!(grid.occupied().count(), 5 * 5 * 5);
+            assert_eq!(grid.state(grid.center()), CellState::Alive);
+            for idx in 0..grid.cells.len() {
+                let pos = grid.idx_to_pos(idx);
+                let alive = grid
+                    .rule
+                    .get_neighbour_iter()
+                    .iter()
+                    .filter_map(|dir| grid.resolve(pos + *dir))
+                    .filter(|pos| grid.state(*pos) == CellState::Alive)
+                    .count();
+                assert_eq!(grid.cells.neighbours(idx), alive as u16, "{mode:?} {pos}");
+            }
+        }
+    }
+
+    #[test]
+    fn large_models_are_cropped_or_resized() {
+        let mut grid = AutomatonGrid::default();
+        grid.set_size(UVec3::splat(16));
+        grid.stamp_vox(&cube_model(20), VoxFit::Crop);
+        assert_eq!(grid.size, UVec3::splat(16));
+        assert_eq!(grid.occupied().count(), 16usize.pow(3));
+
+        grid.stamp_vox(&cube_model(20), VoxFit::Resize);
+        assert_eq!(grid.size, UVec3::splat(20));
+        assert_eq!(grid.occupied().count(), 20usize.pow(3));
+    }
+
+    #[test]
+    fn export_then_import_keeps_the_cells() {
+        let mut grid = AutomatonGrid {
+            seed: 2,
+            ..Default::default()
+        };
+        grid.set_size(UVec3::splat(16));
+        let mut bytes = vec![];
+        write_vox(&grid, &mut bytes).unwrap();
+
+        let mut imported = AutomatonGrid::default();
+        imported.set_size(UVec3::splat(16));
+        imported.stamp_vox(&read_vox(&mut bytes.as_slice()).unwrap(), VoxFit::Crop);
+        assert!(imported.cells == grid.cells);
+    }
+}
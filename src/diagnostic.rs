@@ -1,8 +1,17 @@
+use bevy::diagnostic::{Diagnostic, DiagnosticPath, RegisterDiagnostic};
 use bevy::prelude::*;
 use bevy_screen_diagnostics::{
-    ScreenDiagnostics, ScreenDiagnosticsPlugin, ScreenFrameDiagnosticsPlugin,
+    Aggregate, ScreenDiagnostics, ScreenDiagnosticsPlugin, ScreenFrameDiagnosticsPlugin,
 };
 
+/// Percentage of the occupied cells left out of the instances because they
+/// are enclosed.
+pub const CULLED_SHARE: DiagnosticPath = DiagnosticPath::const_new("culled_share");
+/// Occupied cells in the grid, drawn or not.
+pub const TOTAL_CELLS: DiagnosticPath = DiagnosticPath::const_new("total_cells");
+/// Triangles drawn for the grid, by the cubes or the greedy mesh.
+pub const TRIANGLES: DiagnosticPath = DiagnosticPath::const_new("triangles");
+
 pub struct DiagnosticPlugin;
 
 impl Plugin for DiagnosticPlugin {
@@ -11,14 +20,38 @@ impl Plugin for DiagnosticPlugin {
             ScreenDiagnosticsPlugin::default(),
             ScreenFrameDiagnosticsPlugin,
         ))
+        .register_diagnostic(Diagnostic::new(CULLED_SHARE))
+        .register_diagnostic(Diagnostic::new(TOTAL_CELLS))
+        .register_diagnostic(Diagnostic::new(TRIANGLES))
+        .add_systems(Startup, setup_cell_counts)
         .add_systems(PostStartup, toggle) // starts disabled
         .add_systems(Update, update);
     }
 }
 
+/// Shows the share of culled cells right above the total cell count, and
+/// the triangles drawn.
+fn setup_cell_counts(mut diags: ResMut<ScreenDiagnostics>) {
+    diags
+        .add("culled".to_string(), CULLED_SHARE)
+        .aggregate(Aggregate::Value)
+        .format(|v| format!("{v:.0}%"));
+    diags
+        .add("cells".to_string(), TOTAL_CELLS)
+        .aggregate(Aggregate::Value)
+        .format(|v| format!("{v:.0}"));
+    diags
+        .add("triangles".to_string(), TRIANGLES)
+        .aggregate(Aggregate::Value)
+        .format(|v| format!("{v:.0}"));
+}
+
 fn toggle(mut diags: ResMut<ScreenDiagnostics>) {
     diags.modify("ms/frame").toggle();
     diags.modify("fps").toggle();
+    diags.modify("culled").toggle();
+    diags.modify("cells").toggle();
+    diags.modify("triangles").toggle();
 }
 
 fn update(input: Res<ButtonInput<KeyCode>>, diags: ResMut<ScreenDiagnostics>) {
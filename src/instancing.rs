@@ -4,6 +4,7 @@
 use bevy::{
     core_pipeline::core_3d::Transparent3d,
     ecs::{
+        entity::EntityHashMap,
         query::QueryItem,
         system::{lifetimeless::*, SystemParamItem},
     },
@@ -20,18 +21,21 @@ use bevy::{
             RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
         },
         render_resource::*,
-        renderer::RenderDevice,
+        renderer::{RenderDevice, RenderQueue},
         view::{ExtractedView, NoFrustumCulling},
         Render, RenderApp, RenderSet,
     },
 };
 use bytemuck::{Pod, Zeroable};
+use std::sync::Arc;
+
+use crate::lighting::LightUniform;
 
 fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
     commands.spawn((
         meshes.add(Cuboid::new(1.0, 1.0, 1.0)),
         SpatialBundle::INHERITED_IDENTITY,
-        InstanceMaterialData(Vec::new()),
+        InstanceMaterialData::default(),
         // NOTE: Frustum culling is done based on the Aabb of the Mesh and the GlobalTransform.
         // As the cube is at the origin, if its Aabb moves outside the view frustum, all the
         // instanced cubes will be culled.
@@ -43,8 +47,10 @@ fn setup(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
     ));
 }
 
-#[derive(Component, Deref)]
-pub struct InstanceMaterialData(pub Vec<InstanceData>);
+/// The instances to draw, replaced by a new `Arc` every time the grid steps so
+/// the render world can share them without copying and tell when they changed.
+#[derive(Component, Deref, Default)]
+pub struct InstanceMaterialData(pub Arc<Vec<InstanceData>>);
 
 impl ExtractComponent for InstanceMaterialData {
     type QueryData = &'static InstanceMaterialData;
@@ -52,7 +58,7 @@ impl ExtractComponent for InstanceMaterialData {
     type Out = Self;
 
     fn extract_component(item: QueryItem<'_, Self::QueryData>) -> Option<Self> {
-        Some(InstanceMaterialData(item.0.clone()))
+        Some(InstanceMaterialData(Arc::clone(&item.0)))
     }
 }
 
@@ -64,11 +70,14 @@ impl Plugin for CellMaterialPlugin {
         app.sub_app_mut(RenderApp)
             .add_render_command::<Transparent3d, DrawCustom>()
             .init_resource::<SpecializedMeshPipelines<CustomPipeline>>()
+            .init_resource::<InstanceBuffers>()
+            .init_resource::<LightBindGroup>()
             .add_systems(
                 Render,
                 (
                     queue_custom.in_set(RenderSet::QueueMeshes),
                     prepare_instance_buffers.in_set(RenderSet::PrepareResources),
+                    prepare_light_bind_group.in_set(RenderSet::PrepareBindGroups),
                 ),
             );
     }
@@ -84,6 +93,8 @@ pub struct InstanceData {
     pub position: Vec3,
     pub scale: f32,
     pub color: [f32; 4],
+    /// `vertex_ao` of the cell packed by `pack_ao`, low bits first.
+    pub ao: [u32; 2],
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -134,43 +145,120 @@ fn queue_custom(
     }
 }
 
-#[derive(Component)]
+/// The instance buffer of every instanced entity, kept in a resource as the
+/// render world entities are cleared each frame.
+#[derive(Resource, Default)]
+struct InstanceBuffers(EntityHashMap<InstanceBuffer>);
+
 struct InstanceBuffer {
     buffer: Buffer,
+    /// Instances the buffer has room for, it only grows.
+    capacity: usize,
     length: usize,
+    /// The instances last written, frames drawn between two steps reuse them.
+    uploaded: Arc<Vec<InstanceData>>,
+}
+impl InstanceBuffer {
+    fn new(render_device: &RenderDevice, instances: usize) -> Self {
+        let capacity = instances.next_power_of_two();
+        let buffer = render_device.create_buffer(&BufferDescriptor {
+            label: Some("instance data buffer"),
+            size: (capacity * std::mem::size_of::<InstanceData>()) as u64,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        Self {
+            buffer,
+            capacity,
+            length: 0,
+            uploaded: Arc::default(),
+        }
+    }
 }
 
 fn prepare_instance_buffers(
-    mut commands: Commands,
     query: Query<(Entity, &InstanceMaterialData)>,
+    mut buffers: ResMut<InstanceBuffers>,
     render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
 ) {
+    buffers.0.retain(|entity, _| query.contains(*entity));
+
     for (entity, instance_data) in &query {
-        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
-            label: Some("instance data buffer"),
-            contents: bytemuck::cast_slice(instance_data.as_slice()),
-            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
-        });
-        commands.entity(entity).insert(InstanceBuffer {
-            buffer,
-            length: instance_data.len(),
-        });
+        let instances = &instance_data.0;
+        let instance_buffer = buffers
+            .0
+            .entry(entity)
+            .or_insert_with(|| InstanceBuffer::new(&render_device, instances.len()));
+        if Arc::ptr_eq(&instance_buffer.uploaded, instances) {
+            continue;
+        }
+        if instance_buffer.capacity < instances.len() {
+            *instance_buffer = InstanceBuffer::new(&render_device, instances.len());
+        }
+        if !instances.is_empty() {
+            render_queue.write_buffer(
+                &instance_buffer.buffer,
+                0,
+                bytemuck::cast_slice(instances.as_slice()),
+            );
+        }
+        instance_buffer.length = instances.len();
+        instance_buffer.uploaded = Arc::clone(instances);
     }
 }
 
+/// The light uniform and its bind group, rewritten when the light changes.
+#[derive(Resource, Default)]
+struct LightBindGroup {
+    buffer: UniformBuffer<LightUniform>,
+    bind_group: Option<BindGroup>,
+}
+
+fn prepare_light_bind_group(
+    light: Res<LightUniform>,
+    mut light_bind_group: ResMut<LightBindGroup>,
+    custom_pipeline: Res<CustomPipeline>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    if !light.is_changed() && light_bind_group.bind_group.is_some() {
+        return;
+    }
+    let LightBindGroup { buffer, bind_group } = &mut *light_bind_group;
+    buffer.set(light.clone());
+    buffer.write_buffer(&render_device, &render_queue);
+    *bind_group = buffer.binding().map(|binding| {
+        render_device.create_bind_group(
+            "instance light bind group",
+            &custom_pipeline.light_layout,
+            &BindGroupEntries::single(binding),
+        )
+    });
+}
+
 #[derive(Resource)]
 struct CustomPipeline {
     shader: Handle<Shader>,
     mesh_pipeline: MeshPipeline,
+    light_layout: BindGroupLayout,
 }
 
 impl FromWorld for CustomPipeline {
     fn from_world(world: &mut World) -> Self {
         let mesh_pipeline = world.resource::<MeshPipeline>();
+        let light_layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "instance light layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::FRAGMENT,
+                binding_types::uniform_buffer::<LightUniform>(false),
+            ),
+        );
 
         CustomPipeline {
             shader: world.load_asset("shaders/instancing.wgsl"),
             mesh_pipeline: mesh_pipeline.clone(),
+            light_layout,
         }
     }
 }
@@ -186,6 +274,8 @@ impl SpecializedMeshPipeline for CustomPipeline {
         let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
 
         descriptor.vertex.shader = self.shader.clone();
+        // groups 0 and 1 are the view and mesh bind groups of the mesh pipeline
+        descriptor.layout.push(self.light_layout.clone());
         descriptor.vertex.buffers.push(VertexBufferLayout {
             array_stride: std::mem::size_of::<InstanceData>() as u64,
             step_mode: VertexStepMode::Instance,
@@ -200,6 +290,11 @@ impl SpecializedMeshPipeline for CustomPipeline {
                     offset: VertexFormat::Float32x4.size(),
                     shader_location: 4,
                 },
+                VertexAttribute {
+                    format: VertexFormat::Uint32x2,
+                    offset: 2 * VertexFormat::Float32x4.size(),
+                    shader_location: 5,
+                },
             ],
         });
         descriptor.fragment.as_mut().unwrap().shader = self.shader.clone();
@@ -211,22 +306,50 @@ type DrawCustom = (
     SetItemPipeline,
     SetMeshViewBindGroup<0>,
     SetMeshBindGroup<1>,
+    SetLightBindGroup<2>,
     DrawMeshInstanced,
 );
 
+struct SetLightBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetLightBindGroup<I> {
+    type Param = SRes<LightBindGroup>;
+    type ViewQuery = ();
+    type ItemQuery = ();
+
+    #[inline]
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        _entity: Option<()>,
+        light_bind_group: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(bind_group) = &light_bind_group.into_inner().bind_group else {
+            return RenderCommandResult::Failure;
+        };
+        pass.set_bind_group(I, bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
 struct DrawMeshInstanced;
 
 impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
-    type Param = (SRes<RenderAssets<GpuMesh>>, SRes<RenderMeshInstances>);
+    type Param = (
+        SRes<RenderAssets<GpuMesh>>,
+        SRes<RenderMeshInstances>,
+        SRes<InstanceBuffers>,
+    );
     type ViewQuery = ();
-    type ItemQuery = Read<InstanceBuffer>;
+    type ItemQuery = ();
 
     #[inline]
     fn render<'w>(
         item: &P,
         _view: (),
-        instance_buffer: Option<&'w InstanceBuffer>,
-        (meshes, render_mesh_instances): SystemParamItem<'w, '_, Self::Param>,
+        _entity: Option<()>,
+        (meshes, render_mesh_instances, instance_buffers): SystemParamItem<'w, '_, Self::Param>,
         pass: &mut TrackedRenderPass<'w>,
     ) -> RenderCommandResult {
         let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(item.entity())
@@ -236,9 +359,12 @@ impl<P: PhaseItem> RenderCommand<P> for DrawMeshInstanced {
         let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
             return RenderCommandResult::Failure;
         };
-        let Some(instance_buffer) = instance_buffer else {
+        let Some(instance_buffer) = instance_buffers.into_inner().0.get(&item.entity()) else {
             return RenderCommandResult::Failure;
         };
+        if instance_buffer.length == 0 {
+            return RenderCommandResult::Success;
+        }
 
         pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
         pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
@@ -0,0 +1,100 @@
+//! Directional light of the instanced cubes, set from the UI and sent to the
+//! instancing shader as a uniform.
+
+use bevy::{
+    prelude::*,
+    render::{
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_resource::ShaderType,
+    },
+    transform::TransformSystem,
+};
+
+pub struct LightingPlugin;
+impl Plugin for LightingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractResourcePlugin::<LightUniform>::default())
+            .init_resource::<Lighting>()
+            .init_resource::<LightUniform>()
+            .add_systems(
+                PostUpdate,
+                update_light_uniform.after(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct Lighting {
+    /// Angle of the light around the vertical axis, in degrees.
+    pub azimuth: f32,
+    /// Angle of the light above the horizon, in degrees.
+    pub elevation: f32,
+    pub intensity: f32,
+    /// Light reaching the faces turned away from the light.
+    pub ambient: f32,
+    /// Measure the angles from the camera instead of the world axes, so the
+    /// lit side is always the one in view.
+    pub follow_camera: bool,
+    /// Give each face orientation its own brightness and darken the edges,
+    /// so neighbouring cubes can be told apart.
+    pub face_shading: bool,
+    /// How much the corners hidden by neighbouring cells are darkened, from 0 to 1.
+    pub ao_strength: f32,
+}
+impl Default for Lighting {
+    fn default() -> Self {
+        Self {
+            azimuth: 45.0,
+            elevation: 60.0,
+            intensity: 0.8,
+            ambient: 0.35,
+            follow_camera: false,
+            face_shading: true,
+            ao_strength: 0.6,
+        }
+    }
+}
+impl Lighting {
+    /// Unit vector pointing towards the light, before following the camera.
+    pub fn direction(&self) -> Vec3 {
+        let (azimuth, elevation) = (self.azimuth.to_radians(), self.elevation.to_radians());
+        Vec3::new(
+            elevation.cos() * azimuth.sin(),
+            elevation.sin(),
+            elevation.cos() * azimuth.cos(),
+        )
+    }
+}
+
+/// `Lighting` in the layout of the `Light` struct of `instancing.wgsl`.
+#[derive(Resource, ExtractResource, ShaderType, Debug, Clone, Default, PartialEq)]
+pub struct LightUniform {
+    /// World space direction towards the light.
+    pub direction: Vec3,
+    pub intensity: f32,
+    pub ambient: f32,
+    /// 1 to shade by face, 0 not to.
+    pub face_shading: f32,
+    pub ao_strength: f32,
+}
+
+fn update_light_uniform(
+    lighting: Res<Lighting>,
+    camera: Query<&GlobalTransform, With<Camera3d>>,
+    mut uniform: ResMut<LightUniform>,
+) {
+    let mut direction = lighting.direction();
+    if lighting.follow_camera {
+        if let Ok(camera) = camera.get_single() {
+            direction = camera.affine().transform_vector3(direction).normalize();
+        }
+    }
+    // only changes are extracted to the render world
+    uniform.set_if_neq(LightUniform {
+        direction,
+        intensity: lighting.intensity,
+        ambient: lighting.ambient,
+        face_shading: if lighting.face_shading { 1.0 } else { 0.0 },
+        ao_strength: lighting.ao_strength,
+    });
+}
@@ -1,26 +1,33 @@
 use crate::{
-    automaton_grid::{AutomatonGrid, Example, Examples},
     camera::CameraPlugin,
-    cell::CellState,
-    color::ColorMethod,
-    diagnostic::DiagnosticPlugin,
+    diagnostic::{DiagnosticPlugin, CULLED_SHARE, TOTAL_CELLS, TRIANGLES},
     instancing::CellMaterialPlugin,
     instancing::{InstanceData, InstanceMaterialData},
-    rule::{Indexes, NeighbourMethod, Rule},
+    lighting::LightingPlugin,
+    surface::{surface_mesh, GridSurface, RenderMode, SurfacePlugin},
     ui::UiPlugin,
 };
 use bevy::color::palettes::basic::*;
+use bevy::diagnostic::Diagnostics;
 use bevy::prelude::*;
+use cellular_automata_core::{
+    automaton_grid::{AutomatonGrid, Example, Examples},
+    color::ColorMethod,
+    grid::Grid,
+    mesh::{pack_ao, vertex_ao},
+    rule::{Indexes, NeighbourMethod, Rule},
+    scale::ScaleMethod,
+    sparse_grid::SparseGrid,
+};
 use std::f32::consts::TAU;
+use std::sync::Arc;
 use std::time::Duration;
 
-mod automaton_grid;
 mod camera;
-mod cell;
-mod color;
 mod diagnostic;
 mod instancing;
-mod rule;
+mod lighting;
+mod surface;
 mod ui;
 
 fn main() {
@@ -35,17 +42,28 @@ fn main() {
             ..default()
         }))
         .add_plugins(CellMaterialPlugin)
+        .add_plugins(LightingPlugin)
+        .add_plugins(SurfacePlugin)
         .add_plugins(CameraPlugin)
         .add_plugins(DiagnosticPlugin)
         .add_plugins(UiPlugin)
         .insert_resource(ClearColor(Color::srgb(30. / 255., 30. / 255., 46. / 255.)))
         .insert_resource(AutomatonGrid::default())
+        .insert_resource(SparseGrid::default())
+        .init_resource::<Backend>()
         .insert_resource(Examples::default())
+        .init_resource::<CullEnclosed>()
+        .init_resource::<CellScale>()
         .add_systems(Startup, add_examples)
         .add_systems(
             FixedUpdate,
-            update_automaton_grid.run_if(in_state(SimulationState::Running)),
+            (
+                update_automaton_grid::<AutomatonGrid>.run_if(resource_equals(Backend::Bounded)),
+                update_automaton_grid::<SparseGrid>.run_if(resource_equals(Backend::Sparse)),
+            )
+                .run_if(in_state(SimulationState::Running)),
         )
+        .add_systems(Update, switch_backend.run_if(resource_changed::<Backend>))
         .insert_resource(Time::<Fixed>::from_duration(Duration::from_millis(30)))
         .init_state::<SimulationState>()
         .add_event::<TogglePauseEvent>()
@@ -54,27 +72,73 @@ fn main() {
         .run();
 }
 
-fn update_automaton_grid(
-    mut grid: ResMut<AutomatonGrid>,
+/// Steps any grid backend and rebuilds the instances or the greedy mesh from
+/// its occupied cells, depending on the render mode.
+#[allow(clippy::too_many_arguments)]
+fn update_automaton_grid<G: Grid + Resource>(
+    mut grid: ResMut<G>,
+    render_mode: Res<RenderMode>,
+    cull_enclosed: Res<CullEnclosed>,
+    cell_scale: Res<CellScale>,
     mut query: Query<&mut InstanceMaterialData>,
+    mut surface: Query<(&Handle<Mesh>, &mut Visibility), With<GridSurface>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut diagnostics: Diagnostics,
 ) {
     grid.update();
 
+    let grid = &*grid;
     let grid_center = grid.center();
-    let instance_data = &mut query.iter_mut().next().unwrap().0;
-    instance_data.clear();
-    for (idx, cell) in grid.cells.iter().enumerate() {
-        if let CellState::Empty = cell.state {
+    // a new Arc tells the render world the instances changed
+    let instance_data = &mut query.single_mut().0;
+    let mut instances = Vec::with_capacity(instance_data.len());
+
+    if let Ok((handle, mut visibility)) = surface.get_single_mut() {
+        *visibility = Visibility::Hidden;
+        if *render_mode == RenderMode::GreedyMesh {
+            let mut triangles = 0;
+            if let Some(mesh) = surface_mesh(grid) {
+                triangles = mesh.indices().map_or(0, |indices| indices.len() / 3);
+                meshes.insert(handle, mesh);
+                *visibility = Visibility::Inherited;
+            }
+            *instance_data = Arc::new(instances);
+            // the mesh only has exposed faces, no cell is left out as a whole
+            diagnostics.add_measurement(&CULLED_SHARE, || 0.0);
+            diagnostics.add_measurement(&TOTAL_CELLS, || grid.occupied().count() as f64);
+            diagnostics.add_measurement(&TRIANGLES, || triangles as f64);
+            return;
+        }
+    }
+    // gaps between shrunk cubes show the cells they enclose
+    let cull = cull_enclosed.0 && cell_scale.is_full_size();
+    let mut culled = 0;
+    let mut total = 0;
+    for (pos, cell) in grid.occupied() {
+        total += 1;
+        if cull && grid.is_enclosed(pos) {
+            culled += 1;
             continue;
         }
-        let pos = grid.idx_to_pos(idx);
-        let color = grid.get_color_by_idx(idx);
-        instance_data.push(InstanceData {
+        let color = grid.color(pos, cell);
+        let ao = pack_ao(vertex_ao(grid, pos));
+        instances.push(InstanceData {
             position: (pos - grid_center).as_vec3(),
-            scale: 1.0,
-            color: color.to_srgba().to_vec4().into(),
+            scale: cell_scale
+                .method
+                .get_scale(grid, pos, cell, cell_scale.min, cell_scale.max),
+            color: color.to_array(),
+            ao: [ao as u32, (ao >> 32) as u32],
         });
     }
+    // 12 triangles per cube
+    let triangles = instances.len() * 12;
+    *instance_data = Arc::new(instances);
+    diagnostics.add_measurement(&CULLED_SHARE, || {
+        100.0 * culled as f64 / total.max(1) as f64
+    });
+    diagnostics.add_measurement(&TOTAL_CELLS, || total as f64);
+    diagnostics.add_measurement(&TRIANGLES, || triangles as f64);
 }
 
 fn rotate_grid(
@@ -97,6 +161,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::new(&[4, 6, 8, 9, 10]),
             states: 10,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::DistToCenter,
         color_1: YELLOW.into(),
@@ -109,6 +174,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::new(&[5, 6, 7, 12, 13, 15]),
             states: 20,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::StateLerp,
         color_1: YELLOW.into(),
@@ -121,6 +187,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::parse_str("4,6,9-11,16-24").unwrap(),
             states: 35,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::StateLerp,
         color_1: BLUE.into(),
@@ -133,6 +200,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::parse_str("6,7,9").unwrap(),
             states: 15,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::DistToCenter,
         color_1: GREEN.into(),
@@ -145,6 +213,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::parse_str("3").unwrap(),
             states: 25,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::StateLerp,
         color_1: BLUE.into(),
@@ -157,6 +226,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::parse_str("6,7,9,12").unwrap(),
             states: 30,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::StateLerp,
         color_1: YELLOW.into(),
@@ -169,6 +239,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::parse_str("1-3").unwrap(),
             states: 15,
             neighbour_method: NeighbourMethod::VonNeumann,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::StateLerp,
         color_1: YELLOW.into(),
@@ -181,6 +252,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::parse_str("4").unwrap(),
             states: 50,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::StateLerp,
         color_1: WHITE.into(),
@@ -193,6 +265,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::parse_str("3").unwrap(),
             states: 20,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::StateLerp,
         color_1: BLACK.into(),
@@ -205,6 +278,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::parse_str("3").unwrap(),
             states: 13,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::StateLerp,
         color_1: BLACK.into(),
@@ -217,6 +291,7 @@ fn add_examples(mut examples: ResMut<Examples>) {
             birth_rule: Indexes::parse_str("4").unwrap(),
             states: 5,
             neighbour_method: NeighbourMethod::Moore,
+            neighbour_radius: 1,
         },
         color_method: ColorMethod::StateLerp,
         color_1: WHITE.into(),
@@ -224,6 +299,48 @@ fn add_examples(mut examples: ResMut<Examples>) {
     });
 }
 
+/// Which grid is stepped, shown and edited in the panel.
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    /// `AutomatonGrid`, a box of fixed size with a boundary mode.
+    #[default]
+    Bounded,
+    /// `SparseGrid`, unbounded chunks allocated as the pattern grows.
+    Sparse,
+}
+
+/// Carries the rule, colors, seed and initial condition over to the grid
+/// switched to, which starts again from its initial condition.
+fn switch_backend(
+    backend: Res<Backend>,
+    mut bounded: ResMut<AutomatonGrid>,
+    mut sparse: ResMut<SparseGrid>,
+) {
+    if backend.is_added() {
+        return;
+    }
+    match *backend {
+        Backend::Bounded => {
+            bounded.rule = sparse.rule.clone();
+            bounded.color_method = sparse.color_method.clone();
+            bounded.color_1 = sparse.color_1;
+            bounded.color_2 = sparse.color_2;
+            bounded.seed = sparse.seed;
+            bounded.initial_condition = sparse.initial_condition;
+            bounded.reset();
+        }
+        Backend::Sparse => {
+            sparse.rule = bounded.rule.clone();
+            sparse.color_method = bounded.color_method.clone();
+            sparse.color_1 = bounded.color_1;
+            sparse.color_2 = bounded.color_2;
+            sparse.seed = bounded.seed;
+            sparse.initial_condition = bounded.initial_condition;
+            sparse.reset();
+        }
+    }
+}
+
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SimulationState {
     #[default]
@@ -234,6 +351,39 @@ pub enum SimulationState {
 #[derive(Event)]
 pub struct TogglePauseEvent;
 
+/// Leave out of the instances the cells whose six faces touch other cells,
+/// they are hidden whatever the camera angle.
+#[derive(Resource)]
+pub struct CullEnclosed(pub bool);
+impl Default for CullEnclosed {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// Size of the instanced cubes, from `min` to `max` as the method says.
+#[derive(Resource)]
+pub struct CellScale {
+    pub method: ScaleMethod,
+    pub min: f32,
+    pub max: f32,
+}
+impl Default for CellScale {
+    fn default() -> Self {
+        Self {
+            method: ScaleMethod::Constant,
+            min: 0.2,
+            max: 1.0,
+        }
+    }
+}
+impl CellScale {
+    /// Whether every cube fills its cell, so enclosed cells cannot be seen.
+    pub fn is_full_size(&self) -> bool {
+        self.method == ScaleMethod::Constant && self.max >= 1.0
+    }
+}
+
 fn toggle_pause(
     mut event_reader: EventReader<TogglePauseEvent>,
     state: Res<State<SimulationState>>,
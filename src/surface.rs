@@ -0,0 +1,96 @@
+//! Greedy meshed surface of the grid, drawn instead of the instanced cubes.
+
+use crate::instancing::InstanceMaterialData;
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        view::NoFrustumCulling,
+    },
+};
+use cellular_automata_core::{grid::Grid, mesh::greedy_quads};
+
+pub struct SurfacePlugin;
+impl Plugin for SurfacePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RenderMode>()
+            .add_systems(PostStartup, setup);
+    }
+}
+
+#[derive(Resource, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    /// One cube instance for every alive or dying cell.
+    #[default]
+    Instances,
+    /// A single mesh of the exposed faces, merged by color.
+    GreedyMesh,
+}
+
+/// Marks the entity holding the greedy mesh.
+#[derive(Component)]
+pub struct GridSurface;
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    instances: Query<Entity, With<InstanceMaterialData>>,
+) {
+    let surface = commands
+        .spawn((
+            PbrBundle {
+                mesh: meshes.add(Cuboid::new(1.0, 1.0, 1.0)),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::WHITE,
+                    unlit: true,
+                    ..default()
+                }),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            GridSurface,
+            // the mesh changes every step but its Aabb is only computed once
+            NoFrustumCulling,
+        ))
+        .id();
+    // child of the instanced cubes so it follows their rotation
+    commands.entity(instances.single()).add_child(surface);
+}
+
+/// Mesh of the greedy quads of `grid`, placed where the instanced cubes would
+/// be, `None` when there is nothing to draw.
+pub fn surface_mesh<G: Grid + ?Sized>(grid: &G) -> Option<Mesh> {
+    let (min, _) = grid.bounding_box();
+    let offset = (min - grid.center()).as_vec3() - Vec3::splat(0.5);
+
+    let quads = greedy_quads(grid);
+    if quads.is_empty() {
+        return None;
+    }
+    let mut positions = Vec::with_capacity(quads.len() * 4);
+    let mut normals = Vec::with_capacity(quads.len() * 4);
+    let mut colors = Vec::with_capacity(quads.len() * 4);
+    let mut indices = Vec::with_capacity(quads.len() * 6);
+    for quad in quads {
+        let first = positions.len() as u32;
+        for corner in quad.corners {
+            positions.push((corner + offset).to_array());
+            normals.push(quad.normal.as_vec3().to_array());
+            // unlit, the lighting settings only shade the instanced cubes
+            colors.push(quad.color.to_linear());
+        }
+        indices.extend([first, first + 1, first + 2, first, first + 2, first + 3]);
+    }
+
+    let mesh = Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD,
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+    .with_inserted_attribute(Mesh::ATTRIBUTE_COLOR, colors)
+    .with_inserted_indices(Indices::U32(indices));
+    Some(mesh)
+}
@@ -1,30 +1,223 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
 use std::time::Duration;
 
 use crate::{
-    automaton_grid::{AutomatonGrid, Examples},
-    color::ColorMethod,
-    rule::{Indexes, NeighbourMethod},
-    SimulationState, TogglePauseEvent,
+    lighting::Lighting, surface::RenderMode, Backend, CellScale, CullEnclosed, SimulationState,
+    TogglePauseEvent,
 };
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use cellular_automata_core::{
+    automaton_grid::{AutomatonGrid, BoundaryMode, Example, Examples},
+    color::{self, ColorMethod},
+    grid::Grid,
+    initial_condition::{InitialCondition, InitialShape},
+    mesh::{MeshFormat, SurfaceMesh},
+    rule::{Indexes, NeighbourMethod, Rule, MAX_NEIGHBOUR_RADIUS},
+    scale::ScaleMethod,
+    sparse_grid::SparseGrid,
+    vox,
+};
 
 pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(EguiPlugin).add_systems(Update, ui_system);
+        app.add_plugins(EguiPlugin).add_systems(
+            Update,
+            (
+                ui_system::<AutomatonGrid>.run_if(resource_equals(Backend::Bounded)),
+                ui_system::<SparseGrid>.run_if(resource_equals(Backend::Sparse)),
+            ),
+        );
+    }
+}
+
+/// A grid backend the panel can edit, on top of what `Grid` offers.
+trait PanelGrid: Grid + Resource {
+    fn generation(&self) -> u64;
+    fn seed(&self) -> u64;
+    fn set_seed(&mut self, seed: u64);
+    fn rule_mut(&mut self) -> &mut Rule;
+    fn recount_neighbours(&mut self);
+    fn initial_condition_mut(&mut self) -> &mut InitialCondition;
+    fn color_method_mut(&mut self) -> &mut ColorMethod;
+    fn colors_mut(&mut self) -> [&mut color::Color; 2];
+    fn set_example(&mut self, example: Example);
+    /// Controls of this backend alone, shown below the simulator buttons.
+    fn backend_ui(&mut self, ui: &mut egui::Ui, ui_state: &mut UiState);
+}
+impl PanelGrid for AutomatonGrid {
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.set_seed(seed);
+    }
+
+    fn rule_mut(&mut self) -> &mut Rule {
+        &mut self.rule
+    }
+
+    fn recount_neighbours(&mut self) {
+        self.recount_neighbours();
+    }
+
+    fn initial_condition_mut(&mut self) -> &mut InitialCondition {
+        &mut self.initial_condition
+    }
+
+    fn color_method_mut(&mut self) -> &mut ColorMethod {
+        &mut self.color_method
+    }
+
+    fn colors_mut(&mut self) -> [&mut color::Color; 2] {
+        [&mut self.color_1, &mut self.color_2]
+    }
+
+    fn set_example(&mut self, example: Example) {
+        self.set_example(example);
+    }
+
+    fn backend_ui(&mut self, ui: &mut egui::Ui, ui_state: &mut UiState) {
+        ui.horizontal(|ui| {
+            ui.label("Snapshot file");
+            if ui_state.snapshot_path.is_empty() {
+                ui_state.snapshot_path = "snapshot.ca3d".to_string();
+            }
+            ui.text_edit_singleline(&mut ui_state.snapshot_path);
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Save snapshot").clicked() {
+                ui_state.snapshot_error = save_snapshot(self, &ui_state.snapshot_path).err();
+            }
+            if ui.button("Load snapshot").clicked() {
+                match load_snapshot(&ui_state.snapshot_path) {
+                    Ok(loaded) => {
+                        let parallel = self.parallel;
+                        *self = loaded;
+                        self.parallel = parallel;
+                        ui_state.snapshot_error = None;
+                    }
+                    Err(err) => ui_state.snapshot_error = Some(err),
+                }
+            }
+        });
+        if let Some(error) = &ui_state.snapshot_error {
+            ui.colored_label(egui::Color32::RED, error);
+        }
+
+        ui.horizontal(|ui| {
+            if ui.button("Import .vox").clicked() {
+                let fit = if ui_state.vox_resize {
+                    vox::VoxFit::Resize
+                } else {
+                    vox::VoxFit::Crop
+                };
+                match import_vox(&ui_state.vox_path) {
+                    Ok(model) => {
+                        self.stamp_vox(&model, fit);
+                        ui_state.vox_error = None;
+                    }
+                    Err(err) => ui_state.vox_error = Some(err),
+                }
+            }
+            ui.checkbox(&mut ui_state.vox_resize, "Grow grid to fit imported models");
+        });
+
+        let mut size = self.size;
+        ui.add(egui::Slider::new(&mut size.x, 8..=256).text("Grid width (X)"));
+        ui.add(egui::Slider::new(&mut size.y, 8..=256).text("Grid height (Y)"));
+        ui.add(egui::Slider::new(&mut size.z, 8..=256).text("Grid depth (Z)"));
+        self.set_size(size);
+
+        let mut boundary_mode = self.boundary_mode;
+        egui::ComboBox::from_label("Boundary")
+            .selected_text(format!("{:?}", boundary_mode))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut boundary_mode, BoundaryMode::Toroidal, "Toroidal");
+                ui.selectable_value(&mut boundary_mode, BoundaryMode::Dead, "Dead");
+                ui.selectable_value(&mut boundary_mode, BoundaryMode::Reflective, "Reflective");
+                ui.selectable_value(&mut boundary_mode, BoundaryMode::Clamped, "Clamped");
+            });
+        self.set_boundary_mode(boundary_mode);
+
+        ui.checkbox(&mut self.parallel, "Multithreaded update");
+    }
+}
+impl PanelGrid for SparseGrid {
+    fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    fn set_seed(&mut self, seed: u64) {
+        self.set_seed(seed);
+    }
+
+    fn rule_mut(&mut self) -> &mut Rule {
+        &mut self.rule
+    }
+
+    fn recount_neighbours(&mut self) {
+        self.recount_neighbours();
+    }
+
+    fn initial_condition_mut(&mut self) -> &mut InitialCondition {
+        &mut self.initial_condition
+    }
+
+    fn color_method_mut(&mut self) -> &mut ColorMethod {
+        &mut self.color_method
+    }
+
+    fn colors_mut(&mut self) -> [&mut color::Color; 2] {
+        [&mut self.color_1, &mut self.color_2]
+    }
+
+    fn set_example(&mut self, example: Example) {
+        self.set_example(example);
+    }
+
+    fn backend_ui(&mut self, ui: &mut egui::Ui, _ui_state: &mut UiState) {
+        let (min, max) = self.bounding_box();
+        ui.label(format!("{} chunks of 16³ allocated", self.chunk_count()));
+        ui.label(format!("Cells span {}", max - min));
     }
 }
 
 #[derive(Default)]
 struct UiState {
     birth_rule: String,
+    birth_error: Option<String>,
     survival_rule: String,
+    survival_error: Option<String>,
+    rule: String,
+    rule_error: Option<String>,
+    seed: String,
+    snapshot_path: String,
+    snapshot_error: Option<String>,
+    vox_path: String,
+    vox_error: Option<String>,
+    vox_resize: bool,
+    mesh_path: String,
+    mesh_format: MeshFormat,
+    mesh_error: Option<String>,
 }
 
 #[allow(clippy::too_many_arguments)]
-fn ui_system(
-    mut grid: ResMut<AutomatonGrid>,
+fn ui_system<G: PanelGrid>(
+    mut grid: ResMut<G>,
+    mut backend: ResMut<Backend>,
     examples: Res<Examples>,
     mut contexts: EguiContexts,
     mut background_color: ResMut<ClearColor>,
@@ -32,12 +225,24 @@ fn ui_system(
     mut ui_state: Local<UiState>,
     state: Res<State<SimulationState>>,
     mut event_writer: EventWriter<TogglePauseEvent>,
+    mut render_mode: ResMut<RenderMode>,
+    mut cull_enclosed: ResMut<CullEnclosed>,
+    mut lighting: ResMut<Lighting>,
+    mut cell_scale: ResMut<CellScale>,
 ) {
     egui::Window::new("Cellular Automata")
         .default_width(200.0)
         .show(contexts.ctx_mut(), |ui| {
             ui.label("Background Color");
             color_picker(ui, &mut background_color.0);
+            let mut selected = *backend;
+            egui::ComboBox::from_label("Grid")
+                .selected_text(format!("{:?}", selected))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut selected, Backend::Bounded, "Bounded");
+                    ui.selectable_value(&mut selected, Backend::Sparse, "Sparse");
+                });
+            backend.set_if_neq(selected);
             ui.label("Simulator:");
             ui.horizontal(|ui| {
                 if ui.button("Reset").clicked() {
@@ -50,82 +255,312 @@ fn ui_system(
                 if ui.button(label).clicked() {
                     event_writer.send(TogglePauseEvent);
                 }
+                ui.label(format!("Generation {}", grid.generation()));
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Model file");
+                if ui_state.vox_path.is_empty() {
+                    ui_state.vox_path = "grid.vox".to_string();
+                }
+                ui.text_edit_singleline(&mut ui_state.vox_path);
+                if ui.button("Export .vox").clicked() {
+                    ui_state.vox_error = export_vox(&*grid, &ui_state.vox_path).err();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("Mesh file");
+                if ui_state.mesh_path.is_empty() {
+                    ui_state.mesh_path = "grid.obj".to_string();
+                }
+                ui.text_edit_singleline(&mut ui_state.mesh_path);
+            });
+            ui.horizontal(|ui| {
+                let mut format = ui_state.mesh_format;
+                egui::ComboBox::from_label("Format")
+                    .selected_text(format.extension().to_uppercase())
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut format, MeshFormat::Obj, "OBJ");
+                        ui.selectable_value(&mut format, MeshFormat::Stl, "STL");
+                        ui.selectable_value(&mut format, MeshFormat::Ply, "PLY");
+                    });
+                if format != ui_state.mesh_format {
+                    ui_state.mesh_format = format;
+                    let path = Path::new(&ui_state.mesh_path).with_extension(format.extension());
+                    ui_state.mesh_path = path.to_string_lossy().into_owned();
+                }
+                if ui.button("Export mesh").clicked() {
+                    ui_state.mesh_error =
+                        export_mesh(&*grid, &ui_state.mesh_path, ui_state.mesh_format).err();
+                }
+            });
+            if let Some(error) = &ui_state.mesh_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            grid.backend_ui(ui, &mut ui_state);
+            if let Some(error) = &ui_state.vox_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
+
+            ui.horizontal(|ui| {
+                ui.label("Seed");
+                let seed = ui.text_edit_singleline(&mut ui_state.seed);
+                if seed.changed() {
+                    if let Ok(seed) = ui_state.seed.trim().parse() {
+                        grid.set_seed(seed);
+                    }
+                } else if !seed.has_focus() {
+                    ui_state.seed = grid.seed().to_string();
+                }
+                if ui.button("New seed").clicked() {
+                    grid.set_seed(rand::random());
+                }
             });
 
-            let mut size = grid.size;
-            ui.add(egui::Slider::new(&mut size, 32..=96).text("Grid size"));
-            grid.set_size(size);
+            egui::ComboBox::from_label("Render mode")
+                .selected_text(format!("{:?}", *render_mode))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut *render_mode, RenderMode::Instances, "Instances");
+                    ui.selectable_value(&mut *render_mode, RenderMode::GreedyMesh, "Greedy mesh");
+                });
+            ui.add_enabled(
+                cell_scale.is_full_size(),
+                egui::Checkbox::new(&mut cull_enclosed.0, "Hide enclosed cells"),
+            )
+            .on_disabled_hover_text("Only with the constant scale method at full size");
 
             let mut millis = update_time.timestep().as_millis() as usize;
             ui.add(egui::Slider::new(&mut millis, 10..=50).text("Step (milliseconds)"));
             update_time.set_timestep(Duration::from_millis(millis as u64));
 
+            ui.add_space(24.0);
+            ui.heading("Lighting");
+            ui.add(egui::Slider::new(&mut lighting.azimuth, 0.0..=360.0).text("Light azimuth"));
+            ui.add(
+                egui::Slider::new(&mut lighting.elevation, -90.0..=90.0).text("Light elevation"),
+            );
+            ui.add(egui::Slider::new(&mut lighting.intensity, 0.0..=2.0).text("Intensity"));
+            ui.add(egui::Slider::new(&mut lighting.ambient, 0.0..=1.0).text("Ambient"));
+            ui.checkbox(&mut lighting.follow_camera, "Light follows the camera");
+            ui.checkbox(&mut lighting.face_shading, "Shade each face");
+            ui.add(
+                egui::Slider::new(&mut lighting.ao_strength, 0.0..=1.0).text("Ambient occlusion"),
+            );
+
+            ui.add_space(24.0);
+            ui.heading("Initial Condition");
+            let condition = grid.initial_condition_mut();
+            egui::ComboBox::from_label("Shape")
+                .selected_text(format!("{:?}", condition.shape))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut condition.shape,
+                        InitialShape::NoiseCube,
+                        "Noise Cube",
+                    );
+                    ui.selectable_value(
+                        &mut condition.shape,
+                        InitialShape::NoiseSphere,
+                        "Noise Sphere",
+                    );
+                    ui.selectable_value(
+                        &mut condition.shape,
+                        InitialShape::SingleCell,
+                        "Single Cell",
+                    );
+                    ui.selectable_value(
+                        &mut condition.shape,
+                        InitialShape::HollowShell,
+                        "Hollow Shell",
+                    );
+                    ui.selectable_value(
+                        &mut condition.shape,
+                        InitialShape::RandomFill,
+                        "Random Fill",
+                    );
+                    ui.selectable_value(
+                        &mut condition.shape,
+                        InitialShape::SymmetricNoise,
+                        "Symmetric Noise",
+                    );
+                });
+            if condition.shape.uses_radius() {
+                ui.add(egui::Slider::new(&mut condition.radius, 1..=24).text("Radius"));
+            }
+            if condition.shape.uses_density() {
+                ui.add(egui::Slider::new(&mut condition.density, 0.0..=1.0).text("Density"));
+            }
+
             ui.add_space(24.0);
             ui.heading("Rules");
+            // the neighbour counts only hold for the offsets they were counted with
+            let rule = grid.rule_mut();
+            let counted_offsets = rule.get_neighbour_iter().to_vec();
+
+            ui.label("Rule (survival/birth/states/neighbours)");
+            let rule_text = ui.text_edit_singleline(&mut ui_state.rule);
+            if rule_text.changed() {
+                match ui_state.rule.parse::<Rule>() {
+                    Ok(parsed) => {
+                        *rule = parsed;
+                        ui_state.rule_error = None;
+                    }
+                    Err(err) => ui_state.rule_error = Some(err.to_string()),
+                }
+            } else if !rule_text.has_focus() {
+                ui_state.rule = rule.to_string();
+                ui_state.rule_error = None;
+            }
+            if let Some(error) = &ui_state.rule_error {
+                ui.colored_label(egui::Color32::RED, error);
+            }
 
             ui.label("Survival");
+            let max_neighbours = rule.get_max_neighbours();
             let survival = ui.text_edit_singleline(&mut ui_state.survival_rule);
             if survival.changed() {
-                if let Some(survival) = Indexes::parse_str(&ui_state.survival_rule) {
-                    grid.rule.survival_rule = survival;
+                match Indexes::parse_with_max(&ui_state.survival_rule, max_neighbours) {
+                    Ok(survival) => {
+                        rule.survival_rule = survival;
+                        ui_state.survival_error = None;
+                    }
+                    Err(err) => ui_state.survival_error = Some(err.to_string()),
                 }
             } else if !survival.has_focus() {
-                ui_state.survival_rule = grid.rule.survival_rule.to_string();
+                ui_state.survival_rule = rule.survival_rule.to_string();
+                ui_state.survival_error = None;
+            }
+            if let Some(error) = &ui_state.survival_error {
+                ui.colored_label(egui::Color32::RED, error);
             }
 
             ui.label("Birth");
             let birth = ui.text_edit_singleline(&mut ui_state.birth_rule);
             if birth.changed() {
-                if let Some(birth) = Indexes::parse_str(&ui_state.birth_rule) {
-                    grid.rule.birth_rule = birth;
+                match Indexes::parse_with_max(&ui_state.birth_rule, max_neighbours) {
+                    Ok(birth) => {
+                        rule.birth_rule = birth;
+                        ui_state.birth_error = None;
+                    }
+                    Err(err) => ui_state.birth_error = Some(err.to_string()),
                 }
             } else if !birth.has_focus() {
-                ui_state.birth_rule = grid.rule.birth_rule.to_string();
+                ui_state.birth_rule = rule.birth_rule.to_string();
+                ui_state.birth_error = None;
+            }
+            if let Some(error) = &ui_state.birth_error {
+                ui.colored_label(egui::Color32::RED, error);
             }
 
-            ui.add(egui::Slider::new(&mut grid.rule.states, 1..=50).text("States"));
+            ui.add(egui::Slider::new(&mut rule.states, 2..=255).text("States"));
 
+            let neighbour_method = match rule.neighbour_method {
+                NeighbourMethod::Moore => "Moore",
+                NeighbourMethod::VonNeumann => "Von Neumann",
+                NeighbourMethod::FacesAndEdges => "Faces and Edges",
+                NeighbourMethod::Corners => "Corners",
+                NeighbourMethod::FaceCenteredCubic => "Face-Centered Cubic",
+                NeighbourMethod::Custom(_) => "Custom",
+            };
             egui::ComboBox::from_label("Neighbour Method")
-                .selected_text(format!("{:?}", grid.rule.neighbour_method))
+                .selected_text(neighbour_method)
                 .show_ui(ui, |ui| {
                     ui.selectable_value(
-                        &mut grid.rule.neighbour_method,
+                        &mut rule.neighbour_method,
                         NeighbourMethod::Moore,
                         "Moore",
                     );
                     ui.selectable_value(
-                        &mut grid.rule.neighbour_method,
+                        &mut rule.neighbour_method,
                         NeighbourMethod::VonNeumann,
                         "Von Neumann",
                     );
+                    ui.selectable_value(
+                        &mut rule.neighbour_method,
+                        NeighbourMethod::FacesAndEdges,
+                        "Faces and Edges",
+                    );
+                    ui.selectable_value(
+                        &mut rule.neighbour_method,
+                        NeighbourMethod::Corners,
+                        "Corners",
+                    );
+                    ui.selectable_value(
+                        &mut rule.neighbour_method,
+                        NeighbourMethod::FaceCenteredCubic,
+                        "Face-Centered Cubic",
+                    );
+                    let custom = matches!(rule.neighbour_method, NeighbourMethod::Custom(_));
+                    if ui.selectable_label(custom, "Custom").clicked() && !custom {
+                        // start editing from the neighbourhood currently in use
+                        let offsets = rule.get_neighbour_iter().to_vec();
+                        rule.neighbour_method = NeighbourMethod::Custom(offsets);
+                    }
+                });
+            if rule.neighbour_method.uses_radius() {
+                ui.add(
+                    egui::Slider::new(&mut rule.neighbour_radius, 1..=MAX_NEIGHBOUR_RADIUS)
+                        .text("Neighbour radius"),
+                );
+            }
+            let radius = (rule.neighbour_radius as i32).max(rule.get_neighbour_reach());
+            if let NeighbourMethod::Custom(offsets) = &mut rule.neighbour_method {
+                ui.collapsing("Custom neighbourhood", |ui| {
+                    // only sets how far the editor reaches, the offsets are the neighbourhood
+                    ui.add(
+                        egui::Slider::new(&mut rule.neighbour_radius, 1..=MAX_NEIGHBOUR_RADIUS)
+                            .text("Editor radius"),
+                    );
+                    neighbourhood_editor(ui, offsets, radius);
                 });
+            }
+            if rule.get_neighbour_iter() != counted_offsets {
+                grid.recount_neighbours();
+            }
+            let color_method = grid.color_method_mut();
             egui::ComboBox::from_label("Color Method")
-                .selected_text(format!("{:?}", grid.color_method))
+                .selected_text(format!("{:?}", color_method))
                 .show_ui(ui, |ui| {
                     ui.selectable_value(
-                        &mut grid.color_method,
+                        color_method,
                         ColorMethod::DistToCenter,
                         "Distance to Center",
                     );
+                    ui.selectable_value(color_method, ColorMethod::StateLerp, "State Lerp");
+                    ui.selectable_value(color_method, ColorMethod::Neighbour, "Neighbours");
+                });
+            for (color, label) in grid.colors_mut().into_iter().zip(["Color A", "Color B"]) {
+                ui.horizontal(|ui| {
+                    let mut picked = (*color).into();
+                    color_picker(ui, &mut picked);
+                    *color = picked.into();
+                    ui.label(label);
+                });
+            }
+            egui::ComboBox::from_label("Scale Method")
+                .selected_text(format!("{:?}", cell_scale.method))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut cell_scale.method, ScaleMethod::Constant, "Constant");
                     ui.selectable_value(
-                        &mut grid.color_method,
-                        ColorMethod::StateLerp,
-                        "State Lerp",
+                        &mut cell_scale.method,
+                        ScaleMethod::DyingState,
+                        "Dying State",
                     );
                     ui.selectable_value(
-                        &mut grid.color_method,
-                        ColorMethod::Neighbour,
+                        &mut cell_scale.method,
+                        ScaleMethod::NeighbourCount,
                         "Neighbours",
                     );
+                    ui.selectable_value(
+                        &mut cell_scale.method,
+                        ScaleMethod::DistToCenter,
+                        "Distance to Center",
+                    );
                 });
-            ui.horizontal(|ui| {
-                color_picker(ui, &mut grid.color_1);
-                ui.label("Color A");
-            });
-            ui.horizontal(|ui| {
-                color_picker(ui, &mut grid.color_2);
-                ui.label("Color B");
-            });
+            ui.add(egui::Slider::new(&mut cell_scale.min, 0.0..=1.0).text("Min scale"));
+            ui.add(egui::Slider::new(&mut cell_scale.max, 0.0..=1.0).text("Max scale"));
 
             ui.add_space(24.0);
             ui.heading("Examples");
@@ -144,6 +579,73 @@ fn ui_system(
         });
 }
 
+fn neighbourhood_editor(ui: &mut egui::Ui, offsets: &mut Vec<IVec3>, radius: i32) {
+    for z in -radius..=radius {
+        ui.label(format!("Layer Z {z:+}"));
+        egui::Grid::new(("neighbourhood_layer", z))
+            .spacing([2.0, 2.0])
+            .show(ui, |ui| {
+                for y in (-radius..=radius).rev() {
+                    for x in -radius..=radius {
+                        let offset = IVec3::new(x, y, z);
+                        if offset == IVec3::ZERO {
+                            ui.label("·");
+                            continue;
+                        }
+                        let mut enabled = offsets.contains(&offset);
+                        if ui.checkbox(&mut enabled, "").changed() {
+                            if enabled {
+                                offsets.push(offset);
+                            } else {
+                                offsets.retain(|o| *o != offset);
+                            }
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+fn save_snapshot(grid: &AutomatonGrid, path: &str) -> Result<(), String> {
+    let file = File::create(path).map_err(|err| format!("could not create {path}, {err}"))?;
+    let mut writer = BufWriter::new(file);
+    grid.write_snapshot(&mut writer)
+        .and_then(|_| writer.flush())
+        .map_err(|err| format!("could not write {path}, {err}"))
+}
+
+fn load_snapshot(path: &str) -> Result<AutomatonGrid, String> {
+    let file = File::open(path).map_err(|err| format!("could not open {path}, {err}"))?;
+    AutomatonGrid::read_snapshot(&mut BufReader::new(file)).map_err(|err| err.to_string())
+}
+
+fn export_vox(grid: &impl Grid, path: &str) -> Result<(), String> {
+    let file = File::create(path).map_err(|err| format!("could not create {path}, {err}"))?;
+    let mut writer = BufWriter::new(file);
+    vox::write_vox(grid, &mut writer)
+        .map_err(|err| err.to_string())
+        .and_then(|_| {
+            writer
+                .flush()
+                .map_err(|err| format!("could not write {path}, {err}"))
+        })
+}
+
+fn export_mesh(grid: &impl Grid, path: &str, format: MeshFormat) -> Result<(), String> {
+    let file = File::create(path).map_err(|err| format!("could not create {path}, {err}"))?;
+    let mut writer = BufWriter::new(file);
+    SurfaceMesh::from_grid(grid)
+        .write(format, &mut writer)
+        .and_then(|_| writer.flush())
+        .map_err(|err| format!("could not write {path}, {err}"))
+}
+
+fn import_vox(path: &str) -> Result<vox::VoxModel, String> {
+    let file = File::open(path).map_err(|err| format!("could not open {path}, {err}"))?;
+    vox::read_vox(&mut BufReader::new(file)).map_err(|err| err.to_string())
+}
+
 fn color_picker(ui: &mut egui::Ui, color: &mut Color) {
     let mut c = [
         (color.to_srgba().red * 255.0) as u8,